@@ -0,0 +1,89 @@
+//! Compares the speed of `iter_slices` across all slice storage backends, with every slice
+//! present, and again after removing most of them.
+//! Run with `cargo bench --bench iter_slices`.
+
+use slice_map::*;
+use slotmap::{DefaultKey, SlotMap};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SLICES: u32 = 10_000;
+const SLICE_LEN: u32 = 8;
+const RUNS: u32 = 100;
+
+// Runs iter_slices repeatedly, returning the average time per full iteration.
+fn time_iter<K, S>(map: &SliceMap<K, u32, S>) -> Duration
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<()>> + Default,
+{
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        let sum: u32 = map
+            .iter_slices()
+            .map(|slice| slice.iter().sum::<u32>())
+            .sum();
+        black_box(sum);
+    }
+    start.elapsed() / RUNS
+}
+
+// Times a filled map, then removes 9 out of every 10 slices and times it again.
+fn bench<K, S>(name: &str, mut map: SliceMap<K, u32, S>, keys: Vec<K>)
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<()>> + Default,
+{
+    let full = time_iter(&map);
+    for (i, key) in keys.into_iter().enumerate() {
+        if i % 10 != 0 {
+            map.remove_slice(key);
+        }
+    }
+    let sparse = time_iter(&map);
+    println!("{name:<16} {full:>12.2?} {sparse:>12.2?}");
+}
+
+fn main() {
+    let items: Vec<Vec<u32>> = (0..SLICES).map(|i| (i..i + SLICE_LEN).collect()).collect();
+    let mut primary = SlotMap::<DefaultKey, ()>::new();
+    let keys: Vec<DefaultKey> = items.iter().map(|_| primary.insert(())).collect();
+
+    println!("{:<16} {:>12} {:>12}", "backend", "full", "10% left");
+
+    let mut map = SlotSliceMap::<DefaultKey, u32>::new();
+    let slot_keys = items.iter().map(|slice| map.add_items(slice)).collect();
+    bench("SlotMap", map, slot_keys);
+
+    let mut map = DenseSliceMap::<DefaultKey, u32>::new();
+    let dense_keys = items.iter().map(|slice| map.add_items(slice)).collect();
+    bench("DenseSlotMap", map, dense_keys);
+
+    let mut map = HopSliceMap::<DefaultKey, u32>::new();
+    let hop_keys = items.iter().map(|slice| map.add_items(slice)).collect();
+    bench("HopSlotMap", map, hop_keys);
+
+    let mut map = SecSliceMap::<DefaultKey, u32>::new();
+    for (key, slice) in keys.iter().zip(&items) {
+        map.add_items(*key, slice);
+    }
+    bench("SecondaryMap", map, keys.clone());
+
+    let mut map = SparseSliceMap::<DefaultKey, u32>::new();
+    for (key, slice) in keys.iter().zip(&items) {
+        map.add_items(*key, slice);
+    }
+    bench("SparseSecondary", map, keys.clone());
+
+    let mut map = HashSliceMap::new();
+    for (id, slice) in (0..SLICES).zip(&items) {
+        map.add_items(id, slice);
+    }
+    bench("HashMap", map, (0..SLICES).collect());
+
+    let mut map = BTreeSliceMap::new();
+    for (id, slice) in (0..SLICES).zip(&items) {
+        map.add_items(id, slice);
+    }
+    bench("BTreeMap", map, (0..SLICES).collect());
+}
@@ -0,0 +1,232 @@
+use core::{marker::PhantomData, ops::Range};
+use slotmap::{Key, SecondaryMap, SlotMap, SparseSecondaryMap};
+
+use alloc::vec::Vec;
+
+use crate::SliceStorage;
+
+/// Generates a SliceMap with several item columns, where a single range storage
+/// drives all columns so they always stay in lock step.
+macro_rules! slice_map_columns {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $slot:ident, $sec:ident, $sparse:ident,
+        $( $col:ident : $arg:ident : $idx:tt ),+
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone)]
+        pub struct $name<K, $($col,)+ S>
+        where
+            K: Clone + Eq,
+            S: SliceStorage<K, Range<u32>>,
+        {
+            pub(crate) items: ($(Vec<$col>,)+), // One Vec per column
+            pub(crate) slices: S,              // Generic slice storage, shared by all columns
+            type_key: PhantomData<K>,
+        }
+
+        impl<K, $($col,)+ S> Default for $name<K, $($col,)+ S>
+        where
+            K: Clone + Eq,
+            S: SliceStorage<K, Range<u32>>,
+        {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl<K, $($col,)+ S> $name<K, $($col,)+ S>
+        where
+            K: Clone + Eq,
+            S: SliceStorage<K, Range<u32>> + Default,
+        {
+            /// Returns a new, empty map.
+            pub fn new() -> Self {
+                Self {
+                    items: ($(Vec::<$col>::new(),)+),
+                    slices: S::default(),
+                    type_key: Default::default(),
+                }
+            }
+
+            /// Returns a new map with the specified initial capacity for every column.
+            pub fn with_capacity(cap: usize) -> Self {
+                Self {
+                    items: ($(Vec::<$col>::with_capacity(cap),)+),
+                    slices: S::default(),
+                    type_key: Default::default(),
+                }
+            }
+
+            /// Clears all columns and slices.
+            pub fn clear(&mut self) {
+                $(self.items.$idx.clear();)+
+                self.slices = S::default();
+            }
+
+            /// Returns every column with all items in all slices.
+            pub fn items(&self) -> ($(&[$col],)+) {
+                ($(&self.items.$idx[..],)+)
+            }
+
+            /// How many items are contained in all slices. All columns have the same length.
+            pub fn items_len(&self) -> usize {
+                self.items.0.len()
+            }
+
+            /// True if no items
+            pub fn is_empty(&self) -> bool {
+                self.items.0.is_empty()
+            }
+
+            /// How many slices are contained in the map.
+            pub fn slices_len(&self) -> usize {
+                self.slices.iter().count()
+            }
+
+            /// True if the key points to a slice in this map.
+            pub fn contains_key(&self, key: K) -> bool {
+                self.slices.get(&key).is_some()
+            }
+
+            /// Returns the slice of every column for this key.
+            pub fn get_slice(&self, key: K) -> Option<($(&[$col],)+)> {
+                let range = self.slices.get(&key)?;
+                let range = range.start as usize..range.end as usize;
+                Some(($(&self.items.$idx[range.clone()],)+))
+            }
+
+            /// Returns the mutable slice of every column for this key.
+            pub fn get_slice_mut(&mut self, key: K) -> Option<($(&mut [$col],)+)> {
+                let range = self.slices.get(&key)?;
+                let range = range.start as usize..range.end as usize;
+                Some(($(&mut self.items.$idx[range.clone()],)+))
+            }
+
+            /// Returns an iterator for the slices of every column.
+            pub fn iter_slices(
+                &self,
+            ) -> impl ExactSizeIterator<Item = ($(&[$col],)+)> + '_ {
+                self.slices.values().map(move |range| {
+                    let range = range.start as usize..range.end as usize;
+                    ($(&self.items.$idx[range.clone()],)+)
+                })
+            }
+
+            /// Returns an iterator for the slices of every column along with their keys.
+            pub fn iter_keys_and_slices(
+                &self,
+            ) -> impl ExactSizeIterator<Item = (K, ($(&[$col],)+))> + '_ {
+                self.slices.iter().map(move |(key, range)| {
+                    let range = range.start as usize..range.end as usize;
+                    (key, ($(&self.items.$idx[range.clone()],)+))
+                })
+            }
+
+            /// Removes a slice by key from every column. Warning: Will cause all items to "shift" to
+            /// occupy the removed space, and all slices will be updated with the new indices.
+            pub fn remove_slice(&mut self, key: K) -> Option<Range<u32>> {
+                let removed_slice = self.slices.remove(&key)?;
+                let removed = removed_slice.start as usize..removed_slice.end as usize;
+                $(self.items.$idx.drain(removed.clone());)+
+
+                // Adjust the slices of all subsequent slices
+                let offset = removed_slice.end - removed_slice.start;
+                for slice in self.slices.values_mut() {
+                    if slice.start >= removed_slice.end {
+                        slice.start -= offset;
+                        slice.end -= offset;
+                    }
+                }
+
+                Some(removed_slice)
+            }
+        }
+
+        impl<K, $($col,)+ S> $name<K, $($col,)+ S>
+        where
+            K: Clone + Eq,
+            S: SliceStorage<K, Range<u32>>,
+            $($col: Clone,)+
+        {
+            // Appends the items to every column, returning the new range.
+            fn push_items(&mut self, $($arg: &[$col],)+) -> Range<u32> {
+                let lens = [$($arg.len()),+];
+                assert!(
+                    lens.iter().all(|&len| len == lens[0]),
+                    "All columns must receive the same number of items"
+                );
+                let start: u32 = self.items.0.len().try_into().unwrap();
+                $(self.items.$idx.extend($arg.iter().cloned());)+
+                let end: u32 = self.items.0.len().try_into().unwrap();
+                start..end
+            }
+        }
+
+        /// Multi-column SliceMap that uses [slotmap::SlotMap] for range storage
+        pub type $slot<K, $($col),+> = $name<K, $($col,)+ SlotMap<K, Range<u32>>>;
+
+        impl<K, $($col),+> $slot<K, $($col),+>
+        where
+            K: Key,
+            $($col: Clone,)+
+        {
+            /// Creates a new slice with the items for every column, which must all have the same length.
+            /// Will panic if the capacity of [u32::MAX] items is reached.
+            pub fn add_items(&mut self, $($arg: impl AsRef<[$col]>,)+) -> K {
+                let range = self.push_items($($arg.as_ref(),)+);
+                self.slices.insert(range)
+            }
+        }
+
+        /// Multi-column SliceMap that uses [slotmap::SecondaryMap] for range storage
+        pub type $sec<K, $($col),+> = $name<K, $($col,)+ SecondaryMap<K, Range<u32>>>;
+
+        impl<K, $($col),+> $sec<K, $($col),+>
+        where
+            K: Key,
+            $($col: Clone,)+
+        {
+            /// Creates a new slice with the items for every column, which must all have the same length.
+            /// If the key already has a slice, it is replaced, and its items are removed.
+            /// Will panic if the capacity of [u32::MAX] items is reached.
+            pub fn add_items(&mut self, key: K, $($arg: impl AsRef<[$col]>,)+) {
+                self.remove_slice(key);
+                let range = self.push_items($($arg.as_ref(),)+);
+                self.slices.insert(key, range);
+            }
+        }
+
+        /// Multi-column SliceMap that uses [slotmap::SparseSecondaryMap] for range storage
+        pub type $sparse<K, $($col),+> = $name<K, $($col,)+ SparseSecondaryMap<K, Range<u32>>>;
+
+        impl<K, $($col),+> $sparse<K, $($col),+>
+        where
+            K: Key,
+            $($col: Clone,)+
+        {
+            /// Creates a new slice with the items for every column, which must all have the same length.
+            /// If the key already has a slice, it is replaced, and its items are removed.
+            /// Will panic if the capacity of [u32::MAX] items is reached.
+            pub fn add_items(&mut self, key: K, $($arg: impl AsRef<[$col]>,)+) {
+                self.remove_slice(key);
+                let range = self.push_items($($arg.as_ref(),)+);
+                self.slices.insert(key, range);
+            }
+        }
+    };
+}
+
+slice_map_columns!(
+    /// A SliceMap with two item columns sharing the same keys and ranges.
+    /// Both columns are stored in separate contiguous Vecs, but always contain the same number of items.
+    SliceMap2, SlotSliceMap2, SecSliceMap2, SparseSliceMap2,
+    A: a: 0, B: b: 1
+);
+
+slice_map_columns!(
+    /// A SliceMap with three item columns sharing the same keys and ranges.
+    /// All columns are stored in separate contiguous Vecs, but always contain the same number of items.
+    SliceMap3, SlotSliceMap3, SecSliceMap3, SparseSliceMap3,
+    A: a: 0, B: b: 1, C: c: 2
+);
@@ -0,0 +1,405 @@
+use core::marker::PhantomData;
+use core::ops::Range;
+use slotmap::{Key, KeyData};
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Default maximum number of items per chunk, unless a slice needs more.
+pub const DEFAULT_CHUNK_LEN: u32 = 1024;
+
+// Number of slots in each chunk of the slice table.
+const SLOT_CHUNK_LEN: usize = 64;
+
+/// The location of a slice's items, which never span more than one chunk.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ChunkRange {
+    pub(crate) chunk: u32,
+    pub(crate) range: Range<u32>, // Relative to the start of the chunk
+}
+
+// A chunk of items, along with the slot of every slice stored in it.
+#[derive(Debug, Clone)]
+pub(crate) struct Chunk<V> {
+    pub(crate) items: Vec<V>,
+    pub(crate) slots: Vec<u32>,
+}
+
+// A slot of the slice table. As in a SlotMap, its version is odd while it holds a slice.
+#[derive(Debug, Clone)]
+pub(crate) struct Slot {
+    version: u32,
+    slice: Option<ChunkRange>,
+    next_free: u32, // Next vacant slot, while this one is vacant
+}
+
+/// A copy-on-write SliceMap, where items are stored in reference counted chunks.
+/// [CowSliceMap::snapshot] is O(chunks), since it only shares the chunks of items and the chunks
+/// of the slice table, and later writes to either copy only the chunks they touch.
+/// Adding or removing a slice after a snapshot copies its chunk of items and the chunks of the
+/// slice table holding the slices it moves, not the whole table.
+/// Each slice is stored within a single chunk, so slices are still plain `&[V]`.
+/// Chunks emptied by removing slices release their items, and are reused by later slices.
+pub struct CowSliceMap<K, V>
+where
+    K: Key,
+{
+    pub(crate) chunks: Vec<Arc<Chunk<V>>>, // Shared chunks of items
+    pub(crate) slots: Vec<Arc<Vec<Slot>>>, // Shared chunks of the slice table
+    pub(crate) free: u32,                  // First vacant slot, or u32::MAX if none
+    pub(crate) len: usize,                 // How many slots hold a slice
+    pub(crate) chunk_len: u32,             // Maximum items per chunk, unless a slice needs more
+    type_key: PhantomData<K>,
+}
+
+impl<K, V> Clone for CowSliceMap<K, V>
+where
+    K: Key,
+{
+    fn clone(&self) -> Self {
+        self.snapshot()
+    }
+}
+
+impl<K, V> Default for CowSliceMap<K, V>
+where
+    K: Key,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> CowSliceMap<K, V>
+where
+    K: Key,
+{
+    /// Returns a new, empty CowSliceMap with chunks of [DEFAULT_CHUNK_LEN] items.
+    pub fn new() -> Self {
+        Self::with_chunk_len(DEFAULT_CHUNK_LEN)
+    }
+
+    /// Returns a new, empty CowSliceMap with the specified maximum number of items per chunk.
+    /// Smaller chunks make writes after a snapshot cheaper, larger chunks make snapshots cheaper.
+    pub fn with_chunk_len(chunk_len: u32) -> Self {
+        Self {
+            chunks: Vec::new(),
+            slots: Vec::new(),
+            free: u32::MAX,
+            len: 0,
+            chunk_len: chunk_len.max(1),
+            type_key: PhantomData,
+        }
+    }
+
+    /// Returns a copy of the map that shares all chunks with this one, in O(chunks).
+    pub fn snapshot(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+            slots: self.slots.clone(),
+            free: self.free,
+            len: self.len,
+            chunk_len: self.chunk_len,
+            type_key: PhantomData,
+        }
+    }
+
+    /// Clears the CowSliceMap. Snapshots are not affected.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.slots.clear();
+        self.free = u32::MAX;
+        self.len = 0;
+    }
+
+    /// How many items are contained in all slices.
+    pub fn items_len(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.items.len()).sum()
+    }
+
+    /// True if no items
+    pub fn is_empty(&self) -> bool {
+        self.chunks.iter().all(|chunk| chunk.items.is_empty())
+    }
+
+    /// How many slices are contained in the CowSliceMap.
+    pub fn slices_len(&self) -> usize {
+        self.len
+    }
+
+    /// How many chunks are used to store the items.
+    pub fn chunks_len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// True if the key points to a slice in this map.
+    pub fn contains_key(&self, key: K) -> bool {
+        self.slice(key).is_some()
+    }
+
+    /// Returns a slice with all items in a chunk.
+    pub fn get_chunk(&self, chunk: usize) -> Option<&[V]> {
+        Some(&self.chunks.get(chunk)?.items)
+    }
+
+    /// Returns a slice with the items for this key.
+    pub fn get_slice(&self, key: K) -> Option<&[V]> {
+        let slice = self.slice(key)?;
+        let chunk = self.chunks.get(slice.chunk as usize)?;
+        chunk
+            .items
+            .get(slice.range.start as usize..slice.range.end as usize)
+    }
+
+    /// Returns an iterator for slices of items.
+    pub fn iter_slices(&self) -> impl ExactSizeIterator<Item = &[V]> + '_ {
+        self.iter_slots().map(move |(_, slice)| self.resolve(slice))
+    }
+
+    /// Returns an iterator for slices of items along with their keys.
+    pub fn iter_keys_and_slices(&self) -> impl ExactSizeIterator<Item = (K, &[V])> + '_ {
+        self.iter_slots()
+            .map(move |(key, slice)| (key, self.resolve(slice)))
+    }
+
+    // Returns the items of a slice that is known to be valid.
+    fn resolve(&self, slice: &ChunkRange) -> &[V] {
+        &self.chunks[slice.chunk as usize].items
+            [slice.range.start as usize..slice.range.end as usize]
+    }
+
+    // Returns the location of the slice for this key.
+    fn slice(&self, key: K) -> Option<&ChunkRange> {
+        let (index, version) = split_key(key);
+        let slot = self
+            .slots
+            .get(index / SLOT_CHUNK_LEN)?
+            .get(index % SLOT_CHUNK_LEN)?;
+        if slot.version != version {
+            return None;
+        }
+        slot.slice.as_ref()
+    }
+
+    // Returns an iterator for the occupied slots of the slice table, along with their keys.
+    fn iter_slots(&self) -> Slots<'_, K> {
+        Slots {
+            chunks: self.slots.iter(),
+            slots: [].iter(),
+            index: 0,
+            remaining: self.len,
+            type_key: PhantomData,
+        }
+    }
+
+    // Puts a slice in a vacant slot, copying only that slot's chunk if it is shared.
+    fn insert_slot(&mut self, slice: ChunkRange) -> K {
+        let index = if self.free != u32::MAX {
+            let index = self.free;
+            self.free = slot_mut(&mut self.slots, index).next_free;
+            index
+        } else {
+            if self
+                .slots
+                .last()
+                .is_none_or(|last| last.len() == SLOT_CHUNK_LEN)
+            {
+                self.slots
+                    .push(Arc::new(Vec::with_capacity(SLOT_CHUNK_LEN)));
+            }
+            let last = self.slots.len() - 1;
+            let index = last * SLOT_CHUNK_LEN + self.slots[last].len();
+            assert!(
+                index < u32::MAX as usize,
+                "Capacity of u32::MAX slices reached"
+            );
+            Arc::make_mut(self.slots.last_mut().unwrap()).push(Slot {
+                version: 0,
+                slice: None,
+                next_free: u32::MAX,
+            });
+            index as u32
+        };
+        let slot = slot_mut(&mut self.slots, index);
+        slot.version = slot.version.wrapping_add(1);
+        slot.slice = Some(slice);
+        self.len += 1;
+        join_key(index, slot.version)
+    }
+
+    // Empties the slot for this key, copying only that slot's chunk if it is shared.
+    fn remove_slot(&mut self, key: K) -> Option<ChunkRange> {
+        self.slice(key)?;
+        let (index, _) = split_key(key);
+        let index = index as u32;
+        let slot = slot_mut(&mut self.slots, index);
+        slot.version = slot.version.wrapping_add(1);
+        slot.next_free = self.free;
+        self.free = index;
+        self.len -= 1;
+        slot.slice.take()
+    }
+}
+
+// Returns a slot of the slice table, copying its chunk first if it is shared.
+fn slot_mut(slots: &mut [Arc<Vec<Slot>>], index: u32) -> &mut Slot {
+    let index = index as usize;
+    &mut Arc::make_mut(&mut slots[index / SLOT_CHUNK_LEN])[index % SLOT_CHUNK_LEN]
+}
+
+// Splits a key into its slot index and version, which are stored like in a SlotMap.
+fn split_key<K: Key>(key: K) -> (usize, u32) {
+    let value = key.data().as_ffi();
+    ((value & 0xffff_ffff) as usize, (value >> 32) as u32)
+}
+
+// Makes a key from a slot index and an odd version.
+fn join_key<K: Key>(index: u32, version: u32) -> K {
+    KeyData::from_ffi((u64::from(version) << 32) | u64::from(index)).into()
+}
+
+// Iterates the occupied slots of the slice table, in slot order.
+struct Slots<'a, K> {
+    chunks: core::slice::Iter<'a, Arc<Vec<Slot>>>,
+    slots: core::slice::Iter<'a, Slot>,
+    index: u32,
+    remaining: usize,
+    type_key: PhantomData<K>,
+}
+
+impl<'a, K> Iterator for Slots<'a, K>
+where
+    K: Key,
+{
+    type Item = (K, &'a ChunkRange);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(slot) = self.slots.next() else {
+                self.slots = self.chunks.next()?.iter();
+                continue;
+            };
+            let index = self.index;
+            self.index += 1;
+            if let Some(slice) = &slot.slice {
+                self.remaining -= 1;
+                return Some((join_key(index, slot.version), slice));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K> ExactSizeIterator for Slots<'_, K> where K: Key {}
+
+impl<K, V> CowSliceMap<K, V>
+where
+    K: Key,
+    V: Clone, // Clone is required to copy shared chunks on write
+{
+    /// Creates a new slice with the items, which are added to the last chunk if they fit,
+    /// otherwise to the first chunk with enough room, or to a new chunk if none has.
+    /// Only the chunk that receives the items, and the chunk of the slice table that receives
+    /// the new slice, are copied if they are shared with a snapshot.
+    /// Will panic if the capacity of [u32::MAX] items per chunk is reached.
+    pub fn add_items<ITEMS>(&mut self, new_items: ITEMS) -> K
+    where
+        ITEMS: AsRef<[V]>,
+    {
+        let new_items = new_items.as_ref();
+        let len: u32 = new_items.len().try_into().unwrap();
+        // Empty chunks take any slice, even one larger than the chunk length
+        let fits = |chunk: &Arc<Chunk<V>>| {
+            chunk.items.is_empty() || chunk.items.len() as u64 + len as u64 <= self.chunk_len as u64
+        };
+        let last = self.chunks.len().checked_sub(1);
+        let chunk = match last.filter(|&last| fits(&self.chunks[last])) {
+            Some(last) => last,
+            None => match self.chunks.iter().position(fits) {
+                Some(chunk) => chunk,
+                None => {
+                    self.chunks.push(Arc::new(Chunk {
+                        items: Vec::new(),
+                        slots: Vec::new(),
+                    }));
+                    self.chunks.len() - 1
+                }
+            },
+        };
+        let items = &mut Arc::make_mut(&mut self.chunks[chunk]).items;
+        if items.capacity() == 0 {
+            items.reserve_exact(len.max(self.chunk_len) as usize);
+        }
+        let start = items.len() as u32;
+        items.extend_from_slice(new_items);
+        let key = self.insert_slot(ChunkRange {
+            chunk: chunk as u32,
+            range: start..start + len,
+        });
+        let (index, _) = split_key(key);
+        Arc::make_mut(&mut self.chunks[chunk])
+            .slots
+            .push(index as u32);
+        key
+    }
+
+    /// Returns a mutable slice with the items for this key.
+    /// Copies the slice's chunk first if it is shared with a snapshot.
+    pub fn get_slice_mut(&mut self, key: K) -> Option<&mut [V]> {
+        let ChunkRange { chunk, range } = self.slice(key)?.clone();
+        let chunk = self.chunks.get_mut(chunk as usize)?;
+        Arc::make_mut(chunk)
+            .items
+            .get_mut(range.start as usize..range.end as usize)
+    }
+
+    /// Removes a slice by key, returning its chunk and item range within the chunk.
+    /// Only the slices in the same chunk "shift" to occupy the removed space, and only that
+    /// chunk is copied if it is shared with a snapshot, along with the chunks of the slice table
+    /// holding the removed and shifted slices. A chunk left empty releases its items.
+    pub fn remove_slice(&mut self, key: K) -> Option<(u32, Range<u32>)> {
+        let removed = self.remove_slot(key)?;
+        let (index, _) = split_key(key);
+        let chunk = &mut self.chunks[removed.chunk as usize];
+        if removed.range.start == 0 && removed.range.end as usize == chunk.items.len() {
+            // Any slices left in the chunk are empty, and stay valid
+            let slots = chunk
+                .slots
+                .iter()
+                .copied()
+                .filter(|&slot| slot as usize != index);
+            *chunk = Arc::new(Chunk {
+                items: Vec::new(),
+                slots: slots.collect(),
+            });
+        } else {
+            let chunk = Arc::make_mut(chunk);
+            chunk
+                .items
+                .drain(removed.range.start as usize..removed.range.end as usize);
+            chunk.slots.retain(|&slot| slot as usize != index);
+        }
+
+        // Adjust the subsequent slices in the same chunk
+        let offset = removed.range.end - removed.range.start;
+        for &slot in &self.chunks[removed.chunk as usize].slots {
+            let (chunk, index) = (
+                slot as usize / SLOT_CHUNK_LEN,
+                slot as usize % SLOT_CHUNK_LEN,
+            );
+            let shifts = self.slots[chunk][index]
+                .slice
+                .as_ref()
+                .is_some_and(|slice| slice.range.start >= removed.range.end);
+            if shifts && offset > 0 {
+                let slice = slot_mut(&mut self.slots, slot).slice.as_mut().unwrap();
+                slice.range.start -= offset;
+                slice.range.end -= offset;
+            }
+        }
+        Some((removed.chunk, removed.range))
+    }
+}
@@ -0,0 +1,222 @@
+use slotmap::{Key, SecondaryMap};
+
+use alloc::vec::Vec;
+
+use crate::{
+    KeyRemap, SecSliceMap, SliceEntry, SliceMap, SliceStorage, SlotSliceMap, SparseSliceMap,
+};
+
+/// The changes between two versions of a SliceMap, produced by [diff].
+/// Applying it to a copy of the old version with [SecSliceMap::apply] or [SparseSliceMap::apply]
+/// reproduces the new version, including its keys. [SlotSliceMap::apply] keeps the keys of
+/// removed and modified slices, but a SlotMap can't insert a value at a chosen key, so added
+/// slices get new keys. Aliased slices are diffed and patched as independent slices.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct SliceMapDiff<K, V, H = ()> {
+    pub removed: Vec<K>,                     // Keys only present in the old version
+    pub added: Vec<(K, H, Vec<V>)>,          // Slices only present in the new version
+    pub modified: Vec<(K, SliceDiff<V, H>)>, // Slices present in both versions that changed
+}
+
+/// The changes to a single slice that is present in both versions.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct SliceDiff<V, H = ()> {
+    pub header: Option<H>,        // New header, if it changed
+    pub len: u32,                 // New length of the slice
+    pub runs: Vec<(u32, Vec<V>)>, // Runs of changed or new items, by offset within the new slice
+}
+
+impl<K, V, H> SliceMapDiff<K, V, H> {
+    /// True if both versions are equal.
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.added.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Returns the changes needed to turn the old SliceMap into the new one.
+/// Changed items are grouped into runs, so that unchanged items are never included.
+pub fn diff<K, V, S, H>(
+    old: &SliceMap<K, V, S, H>,
+    new: &SliceMap<K, V, S, H>,
+) -> SliceMapDiff<K, V, H>
+where
+    K: Clone + Eq,
+    V: Clone + PartialEq,
+    H: Clone + PartialEq,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
+{
+    let mut result = SliceMapDiff {
+        removed: Vec::new(),
+        added: Vec::new(),
+        modified: Vec::new(),
+    };
+    for (key, _) in old.iter_keys_and_slices() {
+        if !new.contains_key(key.clone()) {
+            result.removed.push(key);
+        }
+    }
+    for (key, header, items) in new.iter_with_headers() {
+        let Some((old_header, old_items)) = old
+            .contains_key(key.clone())
+            .then(|| old.get_header(key.clone()).zip(old.get_slice(key.clone())))
+            .flatten()
+        else {
+            result.added.push((key, header.clone(), items.to_vec()));
+            continue;
+        };
+        let slice_diff = SliceDiff {
+            header: (old_header != header).then(|| header.clone()),
+            len: items.len() as u32,
+            runs: changed_runs(old_items, items),
+        };
+        if slice_diff.header.is_some()
+            || !slice_diff.runs.is_empty()
+            || old_items.len() != items.len()
+        {
+            result.modified.push((key, slice_diff));
+        }
+    }
+    result
+}
+
+// Returns the runs of items in the new slice that differ from, or extend past, the old slice.
+fn changed_runs<V>(old: &[V], new: &[V]) -> Vec<(u32, Vec<V>)>
+where
+    V: Clone + PartialEq,
+{
+    let mut runs: Vec<(u32, Vec<V>)> = Vec::new();
+    let mut run_start = None;
+    for (i, item) in new.iter().enumerate() {
+        let changed = old.get(i) != Some(item);
+        match (changed, run_start) {
+            (true, None) => run_start = Some(i),
+            (false, Some(start)) => {
+                runs.push((start as u32, new[start..i].to_vec()));
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = run_start {
+        runs.push((start as u32, new[start..].to_vec()));
+    }
+    runs
+}
+
+impl<K, V, S, H> SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    V: Clone,
+    H: Clone,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
+{
+    // Applies a diff, using the provided function to insert each added slice.
+    // Slices that change length get their new items at the end of the items, keeping their keys.
+    fn apply_with<F>(&mut self, diff: &SliceMapDiff<K, V, H>, mut insert: F)
+    where
+        F: FnMut(&mut Self, K, H, &[V]),
+    {
+        // Keys that are no longer present are skipped before any lookup, even if stale
+        for key in &diff.removed {
+            if self.contains_key(key.clone()) {
+                self.remove_slice(key.clone());
+            }
+        }
+        for (key, slice_diff) in &diff.modified {
+            if !self.contains_key(key.clone()) {
+                continue;
+            }
+            let (Some(header), Some(items)) =
+                (self.get_header(key.clone()), self.get_slice(key.clone()))
+            else {
+                continue;
+            };
+            let header = slice_diff.header.as_ref().unwrap_or(header).clone();
+            if items.len() == slice_diff.len as usize {
+                *self.get_header_mut(key.clone()).unwrap() = header;
+                let items = self.get_slice_mut(key.clone()).unwrap();
+                patch_runs(items, &slice_diff.runs);
+            } else {
+                let mut items = items.to_vec();
+                items.truncate(slice_diff.len as usize);
+                patch_runs_growing(&mut items, &slice_diff.runs);
+                *self.get_header_mut(key.clone()).unwrap() = header;
+                self.replace_items(key.clone(), &items);
+            }
+        }
+        for (key, header, items) in &diff.added {
+            insert(self, key.clone(), header.clone(), items);
+        }
+    }
+}
+
+// Overwrites runs of items in a slice of the same length.
+fn patch_runs<V: Clone>(items: &mut [V], runs: &[(u32, Vec<V>)]) {
+    for (offset, run) in runs {
+        let start = *offset as usize;
+        items[start..start + run.len()].clone_from_slice(run);
+    }
+}
+
+// Overwrites runs of items, appending the items past the end.
+fn patch_runs_growing<V: Clone>(items: &mut Vec<V>, runs: &[(u32, Vec<V>)]) {
+    for (offset, run) in runs {
+        let start = *offset as usize;
+        let overlap = run.len().min(items.len().saturating_sub(start));
+        items[start..start + overlap].clone_from_slice(&run[..overlap]);
+        items.extend_from_slice(&run[overlap..]);
+    }
+}
+
+impl<K, V, H> SecSliceMap<K, V, H>
+where
+    K: Key,
+    V: Clone,
+    H: Clone,
+{
+    /// Applies a diff produced by [diff], turning a copy of the old version into the new one,
+    /// with the same keys. Changes to keys that are no longer present are ignored.
+    pub fn apply(&mut self, diff: &SliceMapDiff<K, V, H>) {
+        self.apply_with(diff, |map, key, header, items| {
+            map.add_items_with_header(key, header, items)
+        });
+    }
+}
+
+impl<K, V, H> SparseSliceMap<K, V, H>
+where
+    K: Key,
+    V: Clone,
+    H: Clone,
+{
+    /// Applies a diff produced by [diff], turning a copy of the old version into the new one,
+    /// with the same keys. Changes to keys that are no longer present are ignored.
+    pub fn apply(&mut self, diff: &SliceMapDiff<K, V, H>) {
+        self.apply_with(diff, |map, key, header, items| {
+            map.add_items_with_header(key, header, items)
+        });
+    }
+}
+
+impl<K, V, H> SlotSliceMap<K, V, H>
+where
+    K: Key,
+    V: Clone,
+    H: Clone,
+{
+    /// Applies a diff produced by [diff], turning a copy of the old version into the new one.
+    /// Removed and modified slices keep their keys, while added slices get new keys, which are
+    /// returned as a mapping from their keys in the new version.
+    /// Changes to keys that are no longer present are ignored.
+    pub fn apply(&mut self, diff: &SliceMapDiff<K, V, H>) -> KeyRemap<K> {
+        let mut remap = KeyRemap {
+            keys: SecondaryMap::with_capacity(diff.added.len()),
+        };
+        self.apply_with(diff, |map, key, header, items| {
+            remap
+                .keys
+                .insert(key, map.add_items_with_header(header, items));
+        });
+        remap
+    }
+}
@@ -0,0 +1,25 @@
+use core::fmt;
+
+/// Errors returned by SliceMap operations that can fail without panicking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SliceMapError<K> {
+    /// The key is already present in the map that is being added to.
+    KeyCollision(K),
+    /// Two maps that should have identical layouts differ at this key.
+    LayoutMismatch(K),
+}
+
+impl<K> fmt::Display for SliceMapError<K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeyCollision(key) => write!(f, "key {key:?} is already present"),
+            Self::LayoutMismatch(key) => write!(f, "slice layouts differ at key {key:?}"),
+        }
+    }
+}
+
+impl<K> core::error::Error for SliceMapError<K> where K: fmt::Debug {}
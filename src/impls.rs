@@ -0,0 +1,223 @@
+use core::fmt::{self, Debug};
+use core::hash::{Hash, Hasher};
+use core::ops::{Index, IndexMut, Range};
+use slotmap::Key;
+
+use alloc::vec::Vec;
+
+use crate::{
+    BTreeSliceMap, DenseSliceMap, HopSliceMap, IntoIter, KeySliceIter, SecSliceMap, SliceEntry,
+    SliceMap, SliceStorage, SlotSliceMap,
+};
+
+/// Implements collecting item groups into SliceMaps whose storage creates the keys.
+macro_rules! impl_collect_slice_map {
+    ($name:ident) => {
+        impl<K, V, H, ITEMS> FromIterator<ITEMS> for $name<K, V, H>
+        where
+            K: Key,
+            V: Clone,
+            H: Default,
+            ITEMS: AsRef<[V]>,
+        {
+            /// Creates a new slice for each item group in the iterator.
+            fn from_iter<I: IntoIterator<Item = ITEMS>>(iter: I) -> Self {
+                let mut slice_map = Self::new();
+                slice_map.extend(iter);
+                slice_map
+            }
+        }
+
+        impl<K, V, H, ITEMS> Extend<ITEMS> for $name<K, V, H>
+        where
+            K: Key,
+            V: Clone,
+            H: Default,
+            ITEMS: AsRef<[V]>,
+        {
+            /// Adds a new slice for each item group in the iterator.
+            fn extend<I: IntoIterator<Item = ITEMS>>(&mut self, iter: I) {
+                for new_items in iter {
+                    self.add_items(new_items);
+                }
+            }
+        }
+    };
+}
+
+impl_collect_slice_map!(SlotSliceMap);
+impl_collect_slice_map!(DenseSliceMap);
+impl_collect_slice_map!(HopSliceMap);
+
+impl<K, V, S, H> IntoIterator for SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    V: Clone, // Clone is required to copy items shared by aliases
+    S: SliceStorage<K, SliceEntry<H>>,
+{
+    type Item = (K, Vec<V>);
+    type IntoIter = IntoIter<K, V>;
+
+    /// Consumes the SliceMap, returning each key with its items.
+    fn into_iter(self) -> Self::IntoIter {
+        let mut entries: Vec<(K, Range<u32>, Range<u32>, u64)> = self
+            .slices
+            .iter()
+            .map(|(key, entry)| {
+                (
+                    key,
+                    entry.block.clone(),
+                    entry.range.clone(),
+                    entry.block_id,
+                )
+            })
+            .collect();
+        entries.sort_unstable_by_key(|(_, block, range, id)| {
+            (block.start, block.end, *id, range.start)
+        });
+
+        // Split the items from the back, so each block of items can be moved out
+        let mut items = self.items;
+        let mut block_items = Vec::new();
+        let mut slices = Vec::with_capacity(entries.len());
+        for i in (0..entries.len()).rev() {
+            let (key, block, range, id) = &entries[i];
+            let is_first_in_block = i == 0 || entries[i - 1].3 != *id;
+            if i == entries.len() - 1 || entries[i + 1].3 != *id {
+                block_items = items.split_off(block.start as usize);
+            }
+            let local = (range.start - block.start) as usize..(range.end - block.start) as usize;
+            // Aliases get copies, and the first slice in the block takes the items if it covers them all
+            let slice_items = if is_first_in_block && local == (0..block_items.len()) {
+                core::mem::take(&mut block_items)
+            } else {
+                block_items[local].to_vec()
+            };
+            slices.push((key.clone(), slice_items));
+        }
+        slices.reverse();
+
+        IntoIter {
+            slices: slices.into_iter(),
+        }
+    }
+}
+
+impl<'a, K, V, S, H> IntoIterator for &'a SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
+{
+    type Item = (K, &'a [V]);
+    type IntoIter = KeySliceIter<'a, K, V, S, H>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_keys_and_slices()
+    }
+}
+
+impl<K, V, S, H> Index<K> for SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
+{
+    type Output = [V];
+
+    /// Returns the slice for this key. Will panic if the key is invalid.
+    fn index(&self, key: K) -> &Self::Output {
+        self.get_slice(key).expect("invalid SliceMap key")
+    }
+}
+
+impl<K, V, S, H> IndexMut<K> for SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
+{
+    /// Returns the mutable slice for this key. Will panic if the key is invalid.
+    fn index_mut(&mut self, key: K) -> &mut Self::Output {
+        self.get_slice_mut(key).expect("invalid SliceMap key")
+    }
+}
+
+impl<K, V, S, H> PartialEq for SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    V: PartialEq,
+    H: PartialEq,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
+{
+    /// Two SliceMaps are equal if they contain the same keys pointing to equal slices and headers,
+    /// regardless of how the items are laid out.
+    fn eq(&self, other: &Self) -> bool {
+        self.slices_len() == other.slices_len()
+            && self.iter_with_headers().all(|(key, header, slice)| {
+                other.contains_key(key.clone())
+                    && other.get_header(key.clone()) == Some(header)
+                    && other.get_slice(key) == Some(slice)
+            })
+    }
+}
+
+impl<K, V, S, H> Eq for SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    V: Eq,
+    H: Eq,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
+{
+}
+
+// Hash is only provided for storages that iterate in key order, so that equal maps
+// always hash their slices in the same sequence. SparseSecondaryMap and HashMap iterate in
+// hash order, and DenseSlotMap in an order that depends on which slices were removed.
+macro_rules! impl_hash_slice_map {
+    ($name:ident, $($bound:tt)+) => {
+        impl<K, V, H> Hash for $name<K, V, H>
+        where
+            K: $($bound)+,
+            V: Hash,
+            H: Hash,
+        {
+            fn hash<HS: Hasher>(&self, state: &mut HS) {
+                hash_slices(self, state);
+            }
+        }
+    };
+}
+
+impl_hash_slice_map!(SlotSliceMap, Key);
+impl_hash_slice_map!(HopSliceMap, Key);
+impl_hash_slice_map!(SecSliceMap, Key);
+impl_hash_slice_map!(BTreeSliceMap, Clone + Ord + Hash);
+
+// Hashes the slices in storage order, which depends on the keys but not on the item layout.
+fn hash_slices<K, V, S, H, HS>(map: &SliceMap<K, V, S, H>, state: &mut HS)
+where
+    K: Clone + Eq + Hash,
+    V: Hash,
+    H: Hash,
+    S: SliceStorage<K, SliceEntry<H>>,
+    HS: Hasher,
+{
+    let mut len = 0;
+    for (key, entry) in map.slices.iter() {
+        key.hash(state);
+        entry.header.hash(state);
+        map.items[entry.range.start as usize..entry.range.end as usize].hash(state);
+        len += 1;
+    }
+    state.write_usize(len);
+}
+
+impl<K, V, S, H> Debug for SliceMap<K, V, S, H>
+where
+    K: Clone + Eq + Debug,
+    V: Debug,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
+{
+    /// Formats as a map of keys to their slices.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter_keys_and_slices()).finish()
+    }
+}
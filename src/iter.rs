@@ -1,69 +1,362 @@
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
 use core::marker::PhantomData;
-use alloc::boxed::Box;
-use slotmap::Key;
 use core::ops::Range;
 
-use crate::SliceStorage;
+use crate::{SliceEntry, SliceMapError, SliceStorage};
 
-/// Iterator for `SliceMap` that returns slices of items.
-pub struct SliceIter<'a, K, V, S>
+/// Iterator for `SliceMap` that returns slices of items, in the order the items are laid out.
+/// It walks the sorted item index, so it is double-ended for every storage type.
+pub struct SliceIter<'a, K, V, S, H = ()>
 where
-    K: Key,
-    S: SliceStorage<K, Range<u32>>,
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
     V: 'a,
+    H: 'a,
 {
-    pub slice_map: &'a crate::SliceMap<K, V, S>, // Borrowed reference to the SliceMap
-    pub slices: Box<dyn Iterator<Item = &'a Range<u32>> + 'a>, // Generic iterator over slice ranges
+    pub slice_map: &'a crate::SliceMap<K, V, S, H>, // Borrowed reference to the SliceMap
+    pub slices: core::slice::Iter<'a, (Range<u32>, K)>, // Ranges and keys, in item order
     pub type_data: PhantomData<V>,
 }
 
-impl<'a, K, V, S> Iterator for SliceIter<'a, K, V, S>
+impl<'a, K, V, S, H> SliceIter<'a, K, V, S, H>
 where
-    K: Key,
-    S: SliceStorage<K, Range<u32>>,
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
     V: 'a,
+    H: 'a,
+{
+    fn resolve(&self, (range, _): &'a (Range<u32>, K)) -> &'a [V] {
+        &self.slice_map.items[range.start as usize..range.end as usize]
+    }
+}
+
+impl<'a, K, V, S, H> Iterator for SliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
 {
     type Item = &'a [V];
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(slice) = self.slices.next() {
-            self.slice_map.items.get(slice.start as usize..slice.end as usize)
-        } else {
-            None
-        }
+        let next = self.slices.next()?;
+        Some(self.resolve(next))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slices.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let next = self.slices.nth(n)?;
+        Some(self.resolve(next))
+    }
+}
+
+impl<'a, K, V, S, H> DoubleEndedIterator for SliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let next = self.slices.next_back()?;
+        Some(self.resolve(next))
     }
 }
 
-/// Iterator for `SliceMap` that returns slices of items along with their keys.
-pub struct KeySliceIter<'a, K, V, S>
+impl<'a, K, V, S, H> ExactSizeIterator for SliceIter<'a, K, V, S, H>
 where
-    K: Key,
-    S: SliceStorage<K, Range<u32>>,
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
     V: 'a,
+    H: 'a,
 {
-    pub slice_map: &'a crate::SliceMap<K, V, S>, // Borrowed reference to the SliceMap
-    pub slices: Box<dyn Iterator<Item = (K, &'a Range<u32>)> + 'a>, // Generic iterator over key-value pairs
+}
+
+impl<'a, K, V, S, H> FusedIterator for SliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+}
+
+/// Iterator for `SliceMap` that returns slices of items along with their keys, in the order the
+/// items are laid out. It walks the sorted item index, so it is double-ended for every storage type.
+pub struct KeySliceIter<'a, K, V, S, H = ()>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+    pub slice_map: &'a crate::SliceMap<K, V, S, H>, // Borrowed reference to the SliceMap
+    pub slices: core::slice::Iter<'a, (Range<u32>, K)>, // Ranges and keys, in item order
     pub type_data: PhantomData<V>,
 }
 
-impl<'a, K, V, S> Iterator for KeySliceIter<'a, K, V, S>
+impl<'a, K, V, S, H> KeySliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+    fn resolve(&self, (range, key): &'a (Range<u32>, K)) -> (K, &'a [V]) {
+        let items = &self.slice_map.items[range.start as usize..range.end as usize];
+        (key.clone(), items)
+    }
+}
+
+impl<'a, K, V, S, H> Iterator for KeySliceIter<'a, K, V, S, H>
 where
-    K: Key,
-    S: SliceStorage<K, Range<u32>>,
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
     V: 'a,
+    H: 'a,
 {
     type Item = (K, &'a [V]);
 
     fn next(&mut self) -> Option<Self::Item> {
-        // Get the next key and slice pair
-        if let Some((key, slice)) = self.slices.next() {
-            // Attempt to retrieve the slice of items
-            self.slice_map
-                .items
-                .get(slice.start as usize..slice.end as usize)
-                .map(|item_slice| (key, item_slice))
-        } else {
-            None
+        let next = self.slices.next()?;
+        Some(self.resolve(next))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slices.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let next = self.slices.nth(n)?;
+        Some(self.resolve(next))
+    }
+}
+
+impl<'a, K, V, S, H> DoubleEndedIterator for KeySliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let next = self.slices.next_back()?;
+        Some(self.resolve(next))
+    }
+}
+
+impl<'a, K, V, S, H> ExactSizeIterator for KeySliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+}
+
+impl<'a, K, V, S, H> FusedIterator for KeySliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+}
+
+/// Iterator for `SliceMap` that returns slices of items along with their keys and headers, in the
+/// order the items are laid out. It walks the sorted item index, so it is double-ended for every
+/// storage type.
+pub struct HeaderSliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+    pub slice_map: &'a crate::SliceMap<K, V, S, H>, // Borrowed reference to the SliceMap
+    pub slices: core::slice::Iter<'a, (Range<u32>, K)>, // Ranges and keys, in item order
+    pub type_data: PhantomData<V>,
+}
+
+impl<'a, K, V, S, H> HeaderSliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+    fn resolve(&self, (range, key): &'a (Range<u32>, K)) -> (K, &'a H, &'a [V]) {
+        let entry = self.slice_map.slices.get(key).unwrap(); // The index only holds valid keys
+        let items = &self.slice_map.items[range.start as usize..range.end as usize];
+        (key.clone(), &entry.header, items)
+    }
+}
+
+impl<'a, K, V, S, H> Iterator for HeaderSliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+    type Item = (K, &'a H, &'a [V]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.slices.next()?;
+        Some(self.resolve(next))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slices.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let next = self.slices.nth(n)?;
+        Some(self.resolve(next))
+    }
+}
+
+impl<'a, K, V, S, H> DoubleEndedIterator for HeaderSliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let next = self.slices.next_back()?;
+        Some(self.resolve(next))
+    }
+}
+
+impl<'a, K, V, S, H> ExactSizeIterator for HeaderSliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+}
+
+impl<'a, K, V, S, H> FusedIterator for HeaderSliceIter<'a, K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + 'a,
+    V: 'a,
+    H: 'a,
+{
+}
+
+/// Consuming iterator for `SliceMap` that returns each key along with its items, in the order
+/// the items are laid out.
+pub struct IntoIter<K, V> {
+    pub(crate) slices: alloc::vec::IntoIter<(K, Vec<V>)>,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, Vec<V>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.slices.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slices.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.slices.next_back()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K, V> FusedIterator for IntoIter<K, V> {}
+
+/// Iterator for two `SliceMap`s with identical layout, returned by [zip_slices].
+pub struct ZipSliceIter<'a, K, A, B> {
+    pub(crate) a_items: &'a [A],
+    pub(crate) b_items: &'a [B],
+    pub(crate) slices: alloc::vec::IntoIter<(K, Range<u32>)>, // Ranges shared by both maps
+}
+
+impl<'a, K, A, B> ZipSliceIter<'a, K, A, B> {
+    fn resolve(&self, (key, range): (K, Range<u32>)) -> (K, &'a [A], &'a [B]) {
+        let range = range.start as usize..range.end as usize;
+        (key, &self.a_items[range.clone()], &self.b_items[range])
+    }
+}
+
+impl<'a, K, A, B> Iterator for ZipSliceIter<'a, K, A, B> {
+    type Item = (K, &'a [A], &'a [B]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.slices.next()?;
+        Some(self.resolve(next))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slices.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let next = self.slices.nth(n)?;
+        Some(self.resolve(next))
+    }
+}
+
+impl<K, A, B> DoubleEndedIterator for ZipSliceIter<'_, K, A, B> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let next = self.slices.next_back()?;
+        Some(self.resolve(next))
+    }
+}
+
+impl<K, A, B> ExactSizeIterator for ZipSliceIter<'_, K, A, B> {}
+
+impl<K, A, B> FusedIterator for ZipSliceIter<'_, K, A, B> {}
+
+/// Iterates two SliceMaps with identical layout at once, yielding each key with its slice from
+/// both maps. The maps must contain the same keys, in the same storage order, pointing to the
+/// same item ranges, as happens when both are built from the same sequence of keys and lengths.
+/// The layouts are compared once up front, so no per-key lookups are needed while iterating.
+/// Returns [SliceMapError::LayoutMismatch] with the first key that differs otherwise.
+pub fn zip_slices<'a, K, A, B, SA, SB, HA, HB>(
+    a: &'a crate::SliceMap<K, A, SA, HA>,
+    b: &'a crate::SliceMap<K, B, SB, HB>,
+) -> Result<ZipSliceIter<'a, K, A, B>, SliceMapError<K>>
+where
+    K: Clone + Eq,
+    SA: SliceStorage<K, SliceEntry<HA>>,
+    SB: SliceStorage<K, SliceEntry<HB>>,
+{
+    let mut slices = Vec::new();
+    let mut a_slices = a.slices.iter();
+    let mut b_slices = b.slices.iter();
+    loop {
+        match (a_slices.next(), b_slices.next()) {
+            (Some((a_key, a_entry)), Some((b_key, b_entry))) => {
+                if a_key != b_key || a_entry.range != b_entry.range {
+                    return Err(SliceMapError::LayoutMismatch(a_key));
+                }
+                slices.push((a_key, a_entry.range.clone()));
+            }
+            (Some((key, _)), None) | (None, Some((key, _))) => {
+                return Err(SliceMapError::LayoutMismatch(key))
+            }
+            (None, None) => break,
         }
     }
+    Ok(ZipSliceIter {
+        a_items: &a.items,
+        b_items: &b.items,
+        slices: slices.into_iter(),
+    })
 }
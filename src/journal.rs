@@ -0,0 +1,294 @@
+use slotmap::{Key, SlotMap};
+
+use alloc::vec::Vec;
+
+use crate::{ItemKey, SecSliceMap};
+
+/// A single recorded change. Applying an edit returns the edit that reverts it.
+#[derive(Debug, Clone)]
+pub(crate) enum Edit<K, V, H> {
+    Set { key: K, header: H, items: Vec<V> }, // Creates or replaces a slice
+    Remove { key: K },                        // Removes a slice
+    Write { key: K, offset: u32, items: Vec<V> }, // Overwrites items within a slice
+}
+
+/// A SliceMap that records every edit, so that groups of edits can be undone and redone.
+/// Undoing restores both the contents and the original keys, although restored slices are
+/// appended at the end of the items rather than at their original position.
+/// Keys of removed slices stay reserved while the history may still restore them,
+/// until [JournaledSliceMap::clear_history] is called.
+#[derive(Clone)]
+pub struct JournaledSliceMap<K, V, H = ()>
+where
+    K: Key,
+{
+    pub(crate) map: SecSliceMap<K, V, H>,
+    pub(crate) keys: SlotMap<K, ()>, // Allocates keys, including reserved ones
+    pub(crate) undo: Vec<Vec<Edit<K, V, H>>>, // Transactions of inverse edits, oldest first
+    pub(crate) redo: Vec<Vec<Edit<K, V, H>>>, // Transactions of undone edits, most recently undone last
+    pub(crate) current: Option<Vec<Edit<K, V, H>>>, // Open transaction, if any
+}
+
+impl<K, V, H> Default for JournaledSliceMap<K, V, H>
+where
+    K: Key,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, H> JournaledSliceMap<K, V, H>
+where
+    K: Key,
+{
+    /// Returns a new, empty JournaledSliceMap.
+    pub fn new() -> Self {
+        Self {
+            map: SecSliceMap::new(),
+            keys: SlotMap::with_key(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// Returns the underlying SliceMap. Changes can only be made through the journal.
+    pub fn map(&self) -> &SecSliceMap<K, V, H> {
+        &self.map
+    }
+
+    /// Returns the slice for this key.
+    pub fn get_slice(&self, key: K) -> Option<&[V]> {
+        self.map.get_slice(key)
+    }
+
+    /// Returns the header of the slice for this key.
+    pub fn get_header(&self, key: K) -> Option<&H> {
+        self.map.get_header(key)
+    }
+
+    /// True if the key points to a slice in this map.
+    pub fn contains_key(&self, key: K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// How many slices are contained in the map.
+    pub fn slices_len(&self) -> usize {
+        self.map.slices_len()
+    }
+
+    /// True if there is a transaction that can be undone.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.current.as_ref().is_some_and(|edits| !edits.is_empty())
+    }
+
+    /// True if there is a transaction that can be redone.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Starts a transaction. All edits until [JournaledSliceMap::commit_transaction] are undone
+    /// and redone together. Edits made outside a transaction are each a transaction of their own.
+    /// Does nothing if a transaction is already open.
+    pub fn begin_transaction(&mut self) {
+        if self.current.is_none() {
+            self.current = Some(Vec::new());
+        }
+    }
+
+    /// Closes the open transaction, adding it to the undo history if it contains any edits.
+    pub fn commit_transaction(&mut self) {
+        if let Some(edits) = self.current.take() {
+            if !edits.is_empty() {
+                self.undo.push(edits);
+            }
+        }
+    }
+
+    /// Discards the undo and redo history, including the edits of the open transaction,
+    /// and releases the keys of removed slices.
+    pub fn clear_history(&mut self) {
+        if let Some(edits) = &mut self.current {
+            edits.clear();
+        }
+        self.undo.clear();
+        self.redo.clear();
+        let map = &self.map;
+        self.keys.retain(|key, _| map.contains_key(key));
+    }
+
+    // Records the inverse of an edit that was just made, and discards the redo history.
+    fn record(&mut self, inverse: Edit<K, V, H>) {
+        self.redo.clear();
+        match &mut self.current {
+            Some(edits) => edits.push(inverse),
+            None => self.undo.push(alloc::vec![inverse]),
+        }
+    }
+}
+
+impl<K, V, H> JournaledSliceMap<K, V, H>
+where
+    K: Key,
+    V: Clone,
+    H: Clone,
+{
+    /// Adds a new slice with a default header, returning its key.
+    /// Will panic if the capacity of [u32::MAX] items is reached.
+    pub fn add_items<ITEMS>(&mut self, new_items: ITEMS) -> K
+    where
+        ITEMS: AsRef<[V]>,
+        H: Default,
+    {
+        self.add_items_with_header(H::default(), new_items)
+    }
+
+    /// Adds a new slice with a header, returning its key.
+    /// Will panic if the capacity of [u32::MAX] items is reached.
+    pub fn add_items_with_header<ITEMS>(&mut self, header: H, new_items: ITEMS) -> K
+    where
+        ITEMS: AsRef<[V]>,
+    {
+        let key = self.keys.insert(());
+        self.map.add_items_with_header(key, header, new_items);
+        self.record(Edit::Remove { key });
+        key
+    }
+
+    /// Removes a slice, returning its items.
+    /// Warning: Will cause all items to "shift" to occupy the removed space.
+    pub fn remove_slice(&mut self, key: K) -> Option<Vec<V>> {
+        let (header, items) = self.take_slice(key)?;
+        self.record(Edit::Set {
+            key,
+            header,
+            items: items.clone(),
+        });
+        Some(items)
+    }
+
+    /// Replaces the item at this position, returning the previous item.
+    pub fn set_item(&mut self, item: ItemKey<K>, value: V) -> Option<V> {
+        let slice = self.map.get_slice(item.slice)?;
+        let previous = slice.get(item.offset as usize)?.clone();
+        self.write_items(item.slice, item.offset, [value])
+            .then_some(previous)
+    }
+
+    /// Overwrites items in a slice, starting at the offset. Returns false, without changing
+    /// anything, if the key is invalid or the items don't fit in the slice.
+    pub fn write_items<ITEMS>(&mut self, key: K, offset: u32, new_items: ITEMS) -> bool
+    where
+        ITEMS: AsRef<[V]>,
+    {
+        let edit = Edit::Write {
+            key,
+            offset,
+            items: new_items.as_ref().to_vec(),
+        };
+        let Some(inverse) = self.apply(edit) else {
+            return false;
+        };
+        self.record(inverse);
+        true
+    }
+
+    /// Resizes a slice, either truncating it or filling the new items with the value.
+    /// Warning: The resized slice is moved to the end of the items, and all following items "shift".
+    pub fn resize_slice(&mut self, key: K, len: usize, value: V) -> bool {
+        let (Some(header), Some(items)) = (self.map.get_header(key), self.map.get_slice(key))
+        else {
+            return false;
+        };
+        let mut items = items.to_vec();
+        items.resize(len, value);
+        let edit = Edit::Set {
+            key,
+            header: header.clone(),
+            items,
+        };
+        let Some(inverse) = self.apply(edit) else {
+            return false;
+        };
+        self.record(inverse);
+        true
+    }
+
+    /// Reverts the most recent transaction, committing the open transaction first.
+    /// Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.commit_transaction();
+        let Some(edits) = self.undo.pop() else {
+            return false;
+        };
+        let inverse = self.apply_all(edits);
+        self.redo.push(inverse);
+        true
+    }
+
+    /// Re-applies the most recently undone transaction.
+    /// Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        self.commit_transaction();
+        let Some(edits) = self.redo.pop() else {
+            return false;
+        };
+        let inverse = self.apply_all(edits);
+        self.undo.push(inverse);
+        true
+    }
+
+    // Applies a transaction's edits in reverse order, returning the transaction that reverts it.
+    fn apply_all(&mut self, edits: Vec<Edit<K, V, H>>) -> Vec<Edit<K, V, H>> {
+        edits
+            .into_iter()
+            .rev()
+            .map(|edit| {
+                self.apply(edit)
+                    .expect("journal out of sync with its SliceMap")
+            })
+            .collect()
+    }
+
+    // Applies an edit, returning its inverse, or None if the edit doesn't fit the current contents.
+    fn apply(&mut self, edit: Edit<K, V, H>) -> Option<Edit<K, V, H>> {
+        match edit {
+            Edit::Set { key, header, items } => {
+                let inverse = match self.take_slice(key) {
+                    Some((header, items)) => Edit::Set { key, header, items },
+                    None => Edit::Remove { key },
+                };
+                self.map.add_items_with_header(key, header, items);
+                Some(inverse)
+            }
+            Edit::Remove { key } => {
+                let (header, items) = self.take_slice(key)?;
+                Some(Edit::Set { key, header, items })
+            }
+            Edit::Write { key, offset, items } => {
+                let start = offset as usize;
+                let slice = self.map.get_slice(key)?;
+                if start + items.len() > slice.len() {
+                    return None;
+                }
+                let slice = &mut self.map.get_slice_mut(key)?[start..start + items.len()];
+                let previous = slice.to_vec();
+                slice.clone_from_slice(&items);
+                Some(Edit::Write {
+                    key,
+                    offset,
+                    items: previous,
+                })
+            }
+        }
+    }
+
+    // Removes a slice from the map, returning its header and items.
+    fn take_slice(&mut self, key: K) -> Option<(H, Vec<V>)> {
+        let header = self.map.get_header(key)?.clone();
+        let items = self.map.get_slice(key)?.to_vec();
+        self.map.remove_slice(key);
+        Some((header, items))
+    }
+}
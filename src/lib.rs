@@ -12,36 +12,141 @@ pub use traits::*;
 mod iter;
 pub use iter::*;
 
-use core::{marker::PhantomData, ops::Range};
-use slotmap::{Key, SecondaryMap, SlotMap, SparseSecondaryMap};
+mod impls;
+
+mod columns;
+pub use columns::*;
+
+mod nested;
+pub use nested::*;
+
+mod journal;
+pub use journal::*;
+
+mod cow;
+pub use cow::*;
+
+mod diff;
+pub use diff::*;
+
+mod error;
+pub use error::*;
+
+mod merge;
+pub use merge::*;
+
+mod transform;
+
+mod retain;
+
+#[cfg(feature = "rayon")]
+mod par;
+
+use alloc::collections::BTreeMap;
+use core::hash::Hash;
+use core::{
+    marker::PhantomData,
+    ops::{Range, RangeBounds},
+};
+#[allow(deprecated)] // HopSlotMap is deprecated in slotmap, but still offered as a backend
+use slotmap::HopSlotMap;
+use slotmap::{DenseSlotMap, Key, SecondaryMap, SlotMap, SparseSecondaryMap};
+use std::collections::HashMap;
 
 extern crate alloc;
 use alloc::vec::Vec;
 
+/// A slice's range of items, along with its per-slice header.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SliceEntry<H> {
+    pub range: Range<u32>,
+    pub header: H,
+    pub(crate) block: Range<u32>, // Items shared by this slice and its aliases
+    pub(crate) block_id: u64,     // Identifies the block, which may be empty, among its aliases
+    pub(crate) version: u64,      // Map version of the last change to this slice
+}
+
+impl<H> SliceEntry<H> {
+    // Returns the slice's range and block.
+    pub(crate) fn clone_ranges(&self) -> (Range<u32>, Range<u32>) {
+        (self.range.clone(), self.block.clone())
+    }
+
+    // Returns an entry with its own block, which gets an id when inserted.
+    pub(crate) fn new(range: Range<u32>, header: H) -> Self {
+        Self {
+            block: range.clone(),
+            range,
+            header,
+            block_id: 0,
+            version: 0,
+        }
+    }
+}
+
+/// A handle to a sub-range of an existing slice. The range is relative to the start of the
+/// parent slice, so the handle stays valid when other slices are removed.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubKey<K> {
+    pub parent: K,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl<K> SubKey<K> {
+    /// The range of items, relative to the start of the parent slice.
+    pub fn range(&self) -> Range<u32> {
+        self.start..self.end
+    }
+}
+
+/// A handle to a single item, by its slice key and offset within the slice.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ItemKey<K> {
+    pub slice: K,
+    pub offset: u32,
+}
+
 /// This generic SliceMap needs to be provided a Key type, a Value type and a Storage type.
+/// Optionally, each slice can also carry a header of type H, stored alongside its range.
 /// Use [SlotSliceMap] and [SecSliceMap] for storage using SlotMap and SecondarySlotMap, respectively.
-#[derive(Default, Debug, Clone)]
-pub struct SliceMap<K, V, S>
+/// [DenseSliceMap] and [HopSliceMap] create their own keys like [SlotSliceMap], but iterate faster.
+/// Keys don't need to be slotmap keys: [HashSliceMap] and [BTreeSliceMap] accept any hashable or
+/// ordered key, such as names or external ids.
+#[derive(Default, Clone)]
+pub struct SliceMap<K, V, S, H = ()>
 where
-    K: Key,
-    S: SliceStorage<K, Range<u32>>,
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>>,
 {
-    pub(crate) items: Vec<V>, // Generic items
-    pub(crate) slices: S,     // Generic slice storage
+    pub(crate) items: Vec<V>,                  // Generic items
+    pub(crate) slices: S,                      // Generic slice storage
+    pub(crate) index: Vec<(Range<u32>, K)>,    // Slice ranges sorted by start, for item lookups
+    pub(crate) dirty: Option<Vec<Range<u32>>>, // Sorted, merged ranges of changed items, if tracking
+    pub(crate) version: u64,                   // Incremented on every change
+    #[cfg(debug_assertions)]
+    pub(crate) stale: Option<Vec<K>>, // Keys removed as stale, if checking stale keys
     type_key: PhantomData<K>,
+    type_header: PhantomData<H>,
 }
 
-impl<K, V, S> SliceMap<K, V, S>
+impl<K, V, S, H> SliceMap<K, V, S, H>
 where
-    K: Key,
-    S: SliceStorage<K, Range<u32>> + Default,
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
 {
     /// Returns a new SliceMap containing the provided items object.
     pub fn new() -> Self {
         Self {
             items: Vec::new(),
             slices: S::default(),
+            index: Vec::new(),
+            dirty: None,
+            version: 0,
+            #[cfg(debug_assertions)]
+            stale: None,
             type_key: Default::default(),
+            type_header: Default::default(),
         }
     }
 
@@ -50,7 +155,13 @@ where
         Self {
             items: Vec::with_capacity(cap),
             slices: S::default(),
+            index: Vec::new(),
+            dirty: None,
+            version: 0,
+            #[cfg(debug_assertions)]
+            stale: None,
             type_key: Default::default(),
+            type_header: Default::default(),
         }
     }
 
@@ -58,6 +169,55 @@ where
     pub fn clear(&mut self) {
         self.items.clear();
         self.slices = S::default();
+        self.index.clear();
+        if let Some(dirty) = &mut self.dirty {
+            dirty.clear();
+        }
+        self.next_version();
+    }
+
+    /// Returns the version of the map, which changes whenever anything in the map is modified
+    /// through its API, including mutable access.
+    pub fn map_version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the version of a slice, which changes whenever its items or header are modified
+    /// through the SliceMap's API, including mutable access and writes through aliases.
+    /// The version is the map version at the time of the last change, so it never repeats for
+    /// the same key, even if the key is removed and added again.
+    pub fn slice_version(&self, key: K) -> Option<u64> {
+        Some(self.entry(&key)?.version)
+    }
+
+    /// Enables or disables change tracking. While enabled, every range of items written through
+    /// mutable access, added, or shifted by a removal is recorded, so that only those ranges
+    /// need to be copied elsewhere (i.e. to a GPU buffer). Disabling it discards any recorded ranges.
+    pub fn set_change_tracking(&mut self, enabled: bool) {
+        if enabled != self.dirty.is_some() {
+            self.dirty = enabled.then(Vec::new);
+        }
+    }
+
+    /// True if change tracking is enabled.
+    pub fn is_tracking_changes(&self) -> bool {
+        self.dirty.is_some()
+    }
+
+    /// Returns all ranges of items changed since the last call, sorted and with adjacent ranges
+    /// merged. Always empty if change tracking is disabled.
+    pub fn take_dirty_ranges(&mut self) -> Vec<Range<u32>> {
+        let Some(dirty) = &mut self.dirty else {
+            return Vec::new();
+        };
+        // Ranges past the end of the items were removed, and don't need to be updated anymore
+        let len = self.items.len() as u32;
+        let mut ranges = core::mem::take(dirty);
+        ranges.retain_mut(|range| {
+            range.end = range.end.min(len);
+            range.start < range.end
+        });
+        ranges
     }
 
     /// Returns a slice with all items in all slices.
@@ -80,128 +240,809 @@ where
         self.slices.iter().count()
     }
 
+    /// True if the key points to a slice in this SliceMap.
+    pub fn contains_key(&self, key: K) -> bool {
+        self.slices.get(&key).is_some()
+    }
+
+    // Returns the entry for a key passed to the public API, checking for stale keys if not found.
+    fn entry(&self, key: &K) -> Option<&SliceEntry<H>> {
+        let entry = self.slices.get(key);
+        if entry.is_none() {
+            self.check_stale(key);
+        }
+        entry
+    }
+
     /// Returns a slice with the desired range
     pub fn get_slice(&self, key: K) -> Option<&[V]> {
-        let range = self.slices.get(key)?;
+        let range = &self.entry(&key)?.range;
         self.items.get(range.start as usize..range.end as usize)
     }
 
-    /// Returns an iterator for slices of items.
-    pub fn iter_slices(&self) -> SliceIter<K, V, S> {
+    /// Returns a mutable slice with the desired range
+    pub fn get_slice_mut(&mut self, key: K) -> Option<&mut [V]> {
+        let entry = self.entry(&key)?;
+        let (range, block) = (entry.range.clone(), entry.block.clone());
+        self.mark_written(&key, block, range.clone());
+        self.items.get_mut(range.start as usize..range.end as usize)
+    }
+
+    /// Returns the header of the slice with this key.
+    pub fn get_header(&self, key: K) -> Option<&H> {
+        Some(&self.entry(&key)?.header)
+    }
+
+    /// Returns the mutable header of the slice with this key.
+    pub fn get_header_mut(&mut self, key: K) -> Option<&mut H> {
+        self.entry(&key)?;
+        let version = self.next_version();
+        let entry = self.slices.get_mut(&key)?;
+        entry.version = version;
+        Some(&mut entry.header)
+    }
+
+    /// Returns a handle to a sub-range of the slice with this key, relative to its start.
+    /// Returns None if the key is invalid or the range is out of bounds.
+    pub fn sub_slice_key(&self, key: K, range: Range<u32>) -> Option<SubKey<K>> {
+        let slice = &self.entry(&key)?.range;
+        if range.start > range.end || range.end > slice.end - slice.start {
+            return None;
+        }
+        Some(SubKey {
+            parent: key,
+            start: range.start,
+            end: range.end,
+        })
+    }
+
+    /// Returns the items in a sub-slice, resolved from the parent slice's current position.
+    /// Returns None if the parent slice was removed or no longer contains the range.
+    pub fn get_sub_slice(&self, sub: SubKey<K>) -> Option<&[V]> {
+        let slice = self.get_slice(sub.parent)?;
+        slice.get(sub.start as usize..sub.end as usize)
+    }
+
+    /// Returns the mutable items in a sub-slice, resolved from the parent slice's current position.
+    /// Returns None if the parent slice was removed or no longer contains the range.
+    pub fn get_sub_slice_mut(&mut self, sub: SubKey<K>) -> Option<&mut [V]> {
+        let entry = self.entry(&sub.parent)?;
+        let (slice, block) = (&entry.range, entry.block.clone());
+        if sub.start > sub.end || sub.end > slice.end - slice.start {
+            return None;
+        }
+        let range = slice.start + sub.start..slice.start + sub.end;
+        self.mark_written(&sub.parent, block, range.clone());
+        self.items.get_mut(range.start as usize..range.end as usize)
+    }
+
+    /// Returns mutable slices for several keys at once.
+    /// Returns None if any key is invalid, if the same key is used more than once,
+    /// or if any of the slices share items through aliases.
+    pub fn get_many_mut<const N: usize>(&mut self, keys: [K; N]) -> Option<[&mut [V]; N]> {
+        for (i, key) in keys.iter().enumerate() {
+            if keys[..i].contains(key) {
+                return None;
+            }
+        }
+
+        let entries = keys
+            .iter()
+            .map(|key| Some(self.entry(key)?.clone_ranges()))
+            .collect::<Option<Vec<_>>>()?;
+        let ranges: Vec<Range<u32>> = entries.iter().map(|(range, _)| range.clone()).collect();
+        let order = disjoint_order(&ranges)?; // Reject overlaps before recording writes
+        for (key, (range, block)) in keys.into_iter().zip(entries) {
+            self.mark_written(&key, block, range);
+        }
+        split_ranges_mut(&mut self.items, &ranges, &order)
+            .try_into()
+            .ok()
+    }
+
+    /// Returns an iterator for slices of items, in the order their items are laid out.
+    pub fn iter_slices(&self) -> SliceIter<'_, K, V, S, H> {
         SliceIter {
-            slice_map: &self,
-            slices: self.slices.values(),
+            slice_map: self,
+            slices: self.index.iter(),
             type_data: Default::default(),
         }
     }
 
-    /// Returns an iterator for slices of items along with their keys.
-    pub fn iter_keys_and_slices(&self) -> KeySliceIter<K, V, S> {
+    /// Returns an iterator for slices of items along with their keys, in the order their items
+    /// are laid out.
+    pub fn iter_keys_and_slices(&self) -> KeySliceIter<'_, K, V, S, H> {
         KeySliceIter {
-            slice_map: &self,
-            slices: self.slices.iter(),
+            slice_map: self,
+            slices: self.index.iter(),
+            type_data: Default::default(),
+        }
+    }
+
+    /// Returns an iterator for slices of items along with their keys and headers, in the order
+    /// their items are laid out.
+    pub fn iter_with_headers(&self) -> HeaderSliceIter<'_, K, V, S, H> {
+        HeaderSliceIter {
+            slice_map: self,
+            slices: self.index.iter(),
             type_data: Default::default(),
         }
     }
 
     /// Returns an iterator for each individual item.
-    pub fn iter_items(&self) -> impl Iterator<Item = &V> {
+    pub fn iter_items(&self) -> core::slice::Iter<'_, V> {
         self.items.iter() // Returns an iterator over individual items in the items
     }
 
     /// Removes a slice by key. Warning: Will cause all items to "shift" to occupy the removed space,
     /// and all slices will be updated with the new indices.
+    /// If the slice's items are shared with aliases, they are only removed along with the last one.
     pub fn remove_slice(&mut self, key: K) -> Option<Range<u32>> {
-        let removed_slice = self.slices.remove(key)?;
+        let Some(removed) = self.slices.remove(&key) else {
+            self.check_stale(&key);
+            return None;
+        };
+        self.index_remove(&key, &removed.range);
+        self.next_version();
+        self.release_block(removed.block, removed.block_id);
+        Some(removed.range)
+    }
+
+    // Splits a slice in two adjacent slices without moving any items: the slice keeps the items
+    // before the offset, and the returned entry holds the rest, with a copy of the header.
+    // Returns None if the key is invalid or the offset is out of bounds.
+    fn split_entry(&mut self, key: K, at: u32) -> Option<SliceEntry<H>>
+    where
+        H: Clone,
+    {
+        let range = &self.slices.get(&key)?.range;
+        if at > range.end - range.start {
+            return None;
+        }
+        let exclusive = self.ref_count(key.clone())? == 1;
+        let version = self.next_version();
+        let entry = self.slices.get_mut(&key)?;
+        let mid = entry.range.start + at;
+        let tail = SliceEntry {
+            range: mid..entry.range.end,
+            header: entry.header.clone(),
+            // Shared items stay shared by both halves
+            block: if exclusive {
+                mid..entry.block.end
+            } else {
+                entry.block.clone()
+            },
+            block_id: if exclusive { 0 } else { entry.block_id },
+            version: 0,
+        };
+        let old_range = entry.range.clone();
+        entry.range.end = mid;
+        if exclusive {
+            entry.block.end = mid;
+        }
+        entry.version = version;
+        let range = entry.range.clone();
+        self.index_remove(&key, &old_range);
+        self.index_insert(key, range, None);
+        Some(tail)
+    }
+
+    // Drains a block's items if no slice uses them anymore, shifting all subsequent slices.
+    fn release_block(&mut self, block: Range<u32>, block_id: u64) {
+        if self.slices.values().any(|entry| entry.block_id == block_id) {
+            return;
+        }
 
         // Remove the items in the range from items
-        self.items
-            .drain(removed_slice.start as usize..removed_slice.end as usize);
+        self.items.drain(block.start as usize..block.end as usize);
+        self.close_gap(block);
+    }
+
+    // Shifts all slices after a block whose items were just removed.
+    #[allow(clippy::useless_conversion)]
+    fn close_gap(&mut self, block: Range<u32>) {
+        if block.start < block.end {
+            self.mark_dirty(block.start..self.items.len() as u32);
+        }
 
         // Adjust the slices of all subsequent slices
-        let offset = removed_slice.end - removed_slice.start;
-        for slice in self.slices.values_mut() {
-            if slice.start >= removed_slice.end {
-                slice.start = u32::try_from(slice.start - offset).expect("Index out of bounds");
-                slice.end = u32::try_from(slice.end - offset).expect("Index out of bounds");
+        let offset = block.end - block.start;
+        for entry in self.slices.values_mut() {
+            if entry.block.start >= block.end {
+                entry.block.start -= offset;
+                entry.block.end -= offset;
+                entry.range.start =
+                    u32::try_from(entry.range.start - offset).expect("Index out of bounds");
+                entry.range.end =
+                    u32::try_from(entry.range.end - offset).expect("Index out of bounds");
             }
         }
+        let from = self
+            .index
+            .partition_point(|(range, _)| range.start < block.end);
+        for (range, _) in &mut self.index[from..] {
+            range.start -= offset;
+            range.end -= offset;
+        }
+    }
 
-        Some(removed_slice)
+    /// Returns the key of the slice containing the item at this index in [SliceMap::items],
+    /// along with the item's offset within the slice.
+    /// If the item is shared by aliases, any of the slices containing it may be returned.
+    pub fn slice_of_item(&self, index: usize) -> Option<(K, usize)> {
+        let index: u32 = index.try_into().ok()?;
+        let end = self
+            .index
+            .partition_point(|(range, _)| range.start <= index);
+        self.index[..end]
+            .iter()
+            .rev()
+            .find(|(range, _)| range.end > index)
+            .map(|(range, key)| (key.clone(), (index - range.start) as usize))
     }
-}
 
-/// SliceMap that uses [slotmap::SlotMap] for range storage
-pub type SlotSliceMap<K, V> = SliceMap<K, V, SlotMap<K, Range<u32>>>;
+    /// Returns a single item in a slice.
+    pub fn get_item(&self, item: ItemKey<K>) -> Option<&V> {
+        self.get_slice(item.slice)?.get(item.offset as usize)
+    }
 
-impl<K, V> SlotSliceMap<K, V>
-where
-    K: Key,
-    V: Clone, // Clone is required to handle &V inputs
-{
-    /// Creates a new slice with all items from an iterator of owned or borrowed V items.
-    /// Accepts arrays, slices, or any type that implements AsRef<[V]>.
-    /// Will panic if the capacity of [u32::MAX] items is reached.
-    pub fn add_items<ITEMS>(&mut self, new_items: ITEMS) -> K
+    /// Returns a single mutable item in a slice.
+    pub fn get_item_mut(&mut self, item: ItemKey<K>) -> Option<&mut V> {
+        let (slice, block) = self.entry(&item.slice)?.clone_ranges();
+        let index = slice.start.checked_add(item.offset)?;
+        if index >= slice.end {
+            return None;
+        }
+        self.mark_written(&item.slice, block, index..index + 1);
+        self.items.get_mut(index as usize)
+    }
+
+    /// How many slices share this slice's items, including itself. Aliases increase this count.
+    pub fn ref_count(&self, key: K) -> Option<usize> {
+        let block_id = self.entry(&key)?.block_id;
+        Some(
+            self.slices
+                .values()
+                .filter(|entry| entry.block_id == block_id)
+                .count(),
+        )
+    }
+
+    /// Returns an iterator over the items of each slice, visiting items shared by aliases only once.
+    /// Each returned slice contains all items shared by a slice and its aliases.
+    pub fn iter_unique_slices(&self) -> impl Iterator<Item = &[V]> {
+        let mut blocks: Vec<(Range<u32>, u64)> = self
+            .slices
+            .values()
+            .map(|entry| (entry.block.clone(), entry.block_id))
+            .collect();
+        blocks.sort_unstable_by_key(|(block, id)| (block.start, block.end, *id));
+        blocks.dedup_by_key(|(_, id)| *id);
+        blocks
+            .into_iter()
+            .map(|(block, _)| &self.items[block.start as usize..block.end as usize])
+    }
+
+    // Sets the version of an entry that is about to be inserted, and gives it a block id
+    // unless it shares the block of an existing slice. Block ids are unique map versions.
+    pub(crate) fn stamp_entry(&mut self, entry: &mut SliceEntry<H>) {
+        entry.version = self.next_version();
+        if entry.block_id == 0 {
+            entry.block_id = entry.version;
+        }
+    }
+
+    // Increments and returns the map version.
+    pub(crate) fn next_version(&mut self) -> u64 {
+        self.version += 1;
+        self.version
+    }
+
+    // Records a write to a range of items within a block, through the slice with this key.
+    // Marks the range as dirty, and updates the version of every slice that can see the change.
+    pub(crate) fn mark_written(&mut self, key: &K, block: Range<u32>, written: Range<u32>) {
+        self.mark_dirty(written.clone());
+        let version = self.next_version();
+        if let Some(entry) = self.slices.get_mut(key) {
+            entry.version = version;
+        }
+
+        // Only slices in the same block, which are contiguous in the index, can share items
+        let from = self
+            .index
+            .partition_point(|(range, _)| range.start < block.start);
+        let to = self
+            .index
+            .partition_point(|(range, _)| range.start < written.end);
+        for (range, other) in &self.index[from..to] {
+            if range.end > written.start {
+                if let Some(entry) = self.slices.get_mut(other) {
+                    entry.version = version;
+                }
+            }
+        }
+    }
+
+    // Records a write to all items.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn mark_all_written(&mut self) {
+        self.mark_dirty(0..self.items.len() as u32);
+        let version = self.next_version();
+        for entry in self.slices.values_mut() {
+            entry.version = version;
+        }
+    }
+
+    // Records a range of changed items, if tracking changes.
+    pub(crate) fn mark_dirty(&mut self, range: Range<u32>) {
+        if let Some(dirty) = &mut self.dirty {
+            merge_range(dirty, range);
+        }
+    }
+
+    // Keeps the item index up to date after inserting a slice, possibly replacing an existing one.
+    fn index_insert(&mut self, key: K, range: Range<u32>, replaced: Option<SliceEntry<H>>) {
+        if let Some(replaced) = replaced {
+            self.index_remove(&key, &replaced.range);
+        }
+        let at = self
+            .index
+            .partition_point(|(other, _)| other.start <= range.start);
+        self.index.insert(at, (range, key));
+    }
+
+    // Removes a slice from the item index.
+    fn index_remove(&mut self, key: &K, range: &Range<u32>) {
+        let from = self
+            .index
+            .partition_point(|(other, _)| other.start < range.start);
+        if let Some(offset) = self.index[from..]
+            .iter()
+            .position(|(_, other)| other == key)
+        {
+            self.index.remove(from + offset);
+        }
+    }
+
+    // Returns a new entry that shares the items of the slice with this key.
+    // The optional range is relative to the start of the slice.
+    fn alias_entry(&self, key: K, range: Option<Range<u32>>) -> Option<SliceEntry<H>>
     where
-        ITEMS: AsRef<[V]>, // Accepts &[V], [V; LEN], or other AsRef<[V]> types
+        H: Clone,
     {
-        let start: u32 = self.items.len().try_into().unwrap();
+        let source = self.slices.get(&key)?;
+        let range = match range {
+            Some(range) => {
+                if range.start > range.end || range.end > source.range.end - source.range.start {
+                    return None;
+                }
+                source.range.start + range.start..source.range.start + range.end
+            }
+            None => source.range.clone(),
+        };
+        Some(SliceEntry {
+            range,
+            header: source.header.clone(),
+            block: source.block.clone(),
+            block_id: source.block_id,
+            version: 0,
+        })
+    }
+}
 
-        // Extend items with the cloned elements from the input slice
-        self.items.extend(new_items.as_ref().iter().cloned());
+/// Adds a range to a sorted list of ranges, merging it with any ranges it overlaps or touches.
+pub(crate) fn merge_range(ranges: &mut Vec<Range<u32>>, range: Range<u32>) {
+    if range.start >= range.end {
+        return;
+    }
+    let from = ranges.partition_point(|other| other.end < range.start);
+    let to = ranges.partition_point(|other| other.start <= range.end);
+    let mut merged = range;
+    if from < to {
+        merged.start = merged.start.min(ranges[from].start);
+        merged.end = merged.end.max(ranges[to - 1].end);
+    }
+    ranges.splice(from..to, [merged]);
+}
 
-        let end: u32 = self.items.len().try_into().unwrap();
-        self.slices.insert(start..end)
+/// Returns the indices of the ranges in item order, or None if any of the ranges overlap.
+/// Empty ranges come before a range that starts at the same position, so they never overlap it.
+pub(crate) fn disjoint_order(ranges: &[Range<u32>]) -> Option<Vec<usize>> {
+    let mut order: Vec<usize> = (0..ranges.len()).collect();
+    order.sort_unstable_by_key(|&i| (ranges[i].start, ranges[i].end));
+    let mut end = 0;
+    for &i in &order {
+        if ranges[i].start < end {
+            return None;
+        }
+        end = ranges[i].end;
     }
+    Some(order)
 }
 
-/// SliceMap that uses [slotmap::SecondaryMap] for range storage
-pub type SecSliceMap<K, V> = SliceMap<K, V, SecondaryMap<K, Range<u32>>>;
+/// Splits the items into mutable slices, one per range and in the same order.
+/// The order must come from [disjoint_order] for the same ranges.
+pub(crate) fn split_ranges_mut<'a, V>(
+    items: &'a mut [V],
+    ranges: &[Range<u32>],
+    order: &[usize],
+) -> Vec<&'a mut [V]> {
+    // Walk the ranges in item order, splitting off each one from the remaining items
+    let mut result: Vec<Option<&'a mut [V]>> = ranges.iter().map(|_| None).collect();
+    let mut rest = items;
+    let mut offset = 0;
+    for &i in order {
+        let range = &ranges[i];
+        let gap = (range.start - offset) as usize;
+        let (_, tail) = core::mem::take(&mut rest).split_at_mut(gap);
+        let (slice, tail) = tail.split_at_mut((range.end - range.start) as usize);
+        result[i] = Some(slice);
+        rest = tail;
+        offset = range.end;
+    }
+    result.into_iter().map(Option::unwrap).collect()
+}
 
-impl<K, V> SecSliceMap<K, V>
+impl<K, V, S, H> SliceMap<K, V, S, H>
 where
-    K: Key,
+    K: Clone + Eq,
     V: Clone, // Clone is required to handle &V inputs
+    S: SliceStorage<K, SliceEntry<H>> + Default,
 {
-    /// Creates a new slice with all items from an iterable of owned or borrowed V items.
-    /// Accepts arrays, slices, or any other AsRef<[V]> type.
-    /// Will panic if the capacity of [u32::MAX] items is reached.
-    pub fn add_items<ITEMS>(&mut self, key: K, new_items: ITEMS)
-    where
-        ITEMS: AsRef<[V]>, // Accepts &[V], [V; LEN], Vec<V>, or other AsRef<[V]> types
-    {
+    // Appends the items, returning their new range.
+    // Will panic if the capacity of [u32::MAX] items is reached.
+    fn push_items(&mut self, new_items: &[V]) -> Range<u32> {
         let start: u32 = self.items.len().try_into().unwrap();
 
         // Extend items with the cloned elements from the input slice
-        self.items.extend(new_items.as_ref().iter().cloned());
+        self.items.extend(new_items.iter().cloned());
 
         let end: u32 = self.items.len().try_into().unwrap();
-        self.slices.insert(key, start..end);
+        self.mark_dirty(start..end);
+        self.next_version();
+        start..end
+    }
+
+    /// Concatenates the items of slice b onto slice a, and removes b. Items are only moved if
+    /// the slices aren't already adjacent in [SliceMap::items], or are shared with aliases;
+    /// otherwise a simply grows to cover b. Returns false if either key is invalid, or both are equal.
+    /// Warning: When items are moved, all items after a's original position "shift".
+    pub fn join_slices(&mut self, a: K, b: K) -> bool {
+        if a == b {
+            return false;
+        }
+        let (Some(a_entry), Some(b_entry)) = (self.slices.get(&a), self.slices.get(&b)) else {
+            return false;
+        };
+        let (a_range, a_block) = a_entry.clone_ranges();
+        let (b_range, b_block) = b_entry.clone_ranges();
+        let (a_id, b_id) = (a_entry.block_id, b_entry.block_id);
+        let adjacent = a_range.end == a_block.end
+            && a_block.end == b_block.start
+            && b_block.start == b_range.start
+            && a_id != b_id;
+
+        // No other slice may share a's or b's items, or sit empty between them
+        let exclusive = || {
+            self.slices
+                .values()
+                .filter(|entry| {
+                    entry.block_id == a_id
+                        || entry.block_id == b_id
+                        || (entry.block.start >= a_block.end && entry.block.end <= b_block.start)
+                })
+                .count()
+                == 2
+        };
+        if adjacent && exclusive() {
+            // Grow a to cover b, without moving any items
+            let version = self.next_version();
+            self.slices.remove(&b);
+            self.index_remove(&b, &b_range);
+            let entry = self.slices.get_mut(&a).unwrap();
+            entry.range.end = b_range.end;
+            entry.block.end = b_block.end;
+            entry.version = version;
+            self.index_remove(&a, &a_range);
+            self.index_insert(a, a_range.start..b_range.end, None);
+            return true;
+        }
+
+        let mut joined = self.items[a_range.start as usize..a_range.end as usize].to_vec();
+        joined.extend_from_slice(&self.items[b_range.start as usize..b_range.end as usize]);
+        self.remove_slice(b);
+        self.replace_items(a, &joined);
+        true
     }
+
+    // Replaces the items of an existing slice with new ones, appended to the end of the items.
+    // The slice keeps its key and header, and stops sharing items with any aliases.
+    pub(crate) fn replace_items(&mut self, key: K, items: &[V]) {
+        let entry = self.slices.get(&key).unwrap();
+        let (old_range, old_block, old_block_id) =
+            (entry.range.clone(), entry.block.clone(), entry.block_id);
+        let range = self.push_items(items);
+        let version = self.next_version();
+        let entry = self.slices.get_mut(&key).unwrap();
+        entry.range = range.clone();
+        entry.block = range.clone();
+        entry.block_id = version;
+        entry.version = version;
+        self.index_remove(&key, &old_range);
+        self.index_insert(key, range, None);
+        self.release_block(old_block, old_block_id);
+    }
+}
+
+/// Implements the methods of SliceMaps whose storage creates the keys, like [SlotSliceMap].
+macro_rules! impl_allocating_slice_map {
+    ($name:ident) => {
+        impl<K, V, H> $name<K, V, H>
+        where
+            K: Key,
+            V: Clone, // Clone is required to handle &V inputs
+        {
+            /// Creates a new slice with all items from an iterator of owned or borrowed V items.
+            /// Accepts arrays, slices, or any type that implements AsRef<[V]>.
+            /// The slice header is set to its default value.
+            /// Will panic if the capacity of [u32::MAX] items is reached.
+            pub fn add_items<ITEMS>(&mut self, new_items: ITEMS) -> K
+            where
+                ITEMS: AsRef<[V]>, // Accepts &[V], [V; LEN], or other AsRef<[V]> types
+                H: Default,
+            {
+                self.add_items_with_header(H::default(), new_items)
+            }
+
+            /// Creates a new slice with a header and all items from an iterator of owned or borrowed V items.
+            /// Will panic if the capacity of [u32::MAX] items is reached.
+            pub fn add_items_with_header<ITEMS>(&mut self, header: H, new_items: ITEMS) -> K
+            where
+                ITEMS: AsRef<[V]>,
+            {
+                let range = self.push_items(new_items.as_ref());
+                self.insert_entry(SliceEntry::new(range, header))
+            }
+
+            /// Creates a new key for the same items as an existing slice, without copying them.
+            /// The alias starts with a copy of the slice's header. Returns None if the key is invalid.
+            pub fn add_alias(&mut self, key: K) -> Option<K>
+            where
+                H: Clone,
+            {
+                let entry = self.alias_entry(key, None)?;
+                Some(self.insert_entry(entry))
+            }
+
+            /// Creates a new key for a sub-range of an existing slice, without copying its items.
+            /// The range is relative to the start of the slice. Returns None if the key is invalid or
+            /// the range is out of bounds.
+            pub fn add_sub_alias(&mut self, key: K, range: Range<u32>) -> Option<K>
+            where
+                H: Clone,
+            {
+                let entry = self.alias_entry(key, Some(range))?;
+                Some(self.insert_entry(entry))
+            }
+
+            /// Splits a slice in two adjacent slices, without moving any items. The slice keeps the
+            /// items before the offset, and the returned key points to the rest, starting with a copy
+            /// of the header. Returns None if the key is invalid or the offset is out of bounds.
+            pub fn split_slice(&mut self, key: K, at: u32) -> Option<K>
+            where
+                H: Clone,
+            {
+                let entry = self.split_entry(key, at)?;
+                Some(self.insert_entry(entry))
+            }
+
+            /// Appends a copy of a slice's items and header as a new slice, returning its key.
+            /// Returns None if the key is invalid.
+            /// Will panic if the capacity of [u32::MAX] items is reached.
+            pub fn duplicate_slice(&mut self, key: K) -> Option<K>
+            where
+                H: Clone,
+            {
+                let entry = self.slices.get(key)?;
+                let (range, header) = (entry.range.clone(), entry.header.clone());
+                let start: u32 = self.items.len().try_into().unwrap();
+                let end: u32 = (self.items.len() + range.len()).try_into().unwrap();
+                self.items
+                    .extend_from_within(range.start as usize..range.end as usize);
+                self.mark_dirty(start..end);
+                Some(self.insert_entry(SliceEntry::new(start..end, header)))
+            }
+        }
+
+        impl<K, V, H> $name<K, V, H>
+        where
+            K: Key,
+        {
+            /// Moves a slice into another map, without cloning its items, and returns its key in the
+            /// other map. The items are appended to the other map, and the header moves along.
+            /// Returns None if the key is invalid, or if the slice shares its items with aliases.
+            /// Warning: Will cause all items after the slice in this map to "shift" to occupy the removed space.
+            /// Will panic if the capacity of [u32::MAX] items is reached in the other map.
+            pub fn move_slice_to(&mut self, key: K, other: &mut Self) -> Option<K> {
+                if self.ref_count(key)? > 1 {
+                    return None;
+                }
+                let removed = self.slices.remove(key)?;
+                self.index_remove(&key, &removed.range);
+                self.next_version();
+
+                // Move only the slice's range out of its block, and drop any other items in the block
+                let block = removed.block;
+                let start: u32 = other.items.len().try_into().unwrap();
+                let skip = (removed.range.start - block.start) as usize;
+                let len = (removed.range.end - removed.range.start) as usize;
+                let drained = self.items.drain(block.start as usize..block.end as usize);
+                other.items.extend(drained.skip(skip).take(len));
+                let end: u32 = other.items.len().try_into().unwrap();
+                self.close_gap(block);
+
+                other.mark_dirty(start..end);
+                Some(other.insert_entry(SliceEntry::new(start..end, removed.header)))
+            }
+
+            // Inserts a new slice, keeping the item index up to date.
+            fn insert_entry(&mut self, mut entry: SliceEntry<H>) -> K {
+                self.stamp_entry(&mut entry);
+                let range = entry.range.clone();
+                let key = self.slices.insert(entry);
+                self.index_insert(key, range, None);
+                key
+            }
+        }
+    };
 }
 
+/// SliceMap that uses [slotmap::SlotMap] for range storage
+pub type SlotSliceMap<K, V, H = ()> = SliceMap<K, V, SlotMap<K, SliceEntry<H>>, H>;
+
+impl_allocating_slice_map!(SlotSliceMap);
+
+/// SliceMap that uses [slotmap::DenseSlotMap] for range storage, which is faster to iterate
+pub type DenseSliceMap<K, V, H = ()> = SliceMap<K, V, DenseSlotMap<K, SliceEntry<H>>, H>;
+
+impl_allocating_slice_map!(DenseSliceMap);
+
+/// SliceMap that uses [slotmap::HopSlotMap] for range storage, which is faster to iterate
+/// when many slices have been removed. Note that slotmap no longer maintains HopSlotMap.
+#[allow(deprecated)]
+pub type HopSliceMap<K, V, H = ()> = SliceMap<K, V, HopSlotMap<K, SliceEntry<H>>, H>;
+
+impl_allocating_slice_map!(HopSliceMap);
+
+/// Implements the methods of SliceMaps whose slices are added at a chosen key, like [SecSliceMap].
+macro_rules! impl_keyed_slice_map {
+    ($name:ident, $($bound:tt)+) => {
+        impl<K, V, H> $name<K, V, H>
+        where
+            K: $($bound)+,
+            V: Clone, // Clone is required to handle &V inputs
+        {
+            /// Creates a new slice with all items from an iterable of owned or borrowed V items.
+            /// Accepts arrays, slices, or any other AsRef<[V]> type.
+            /// The slice header is set to its default value. If the key already has a slice, it is
+            /// replaced, and its items are removed unless shared by aliases.
+            /// Will panic if the capacity of [u32::MAX] items is reached.
+            pub fn add_items<ITEMS>(&mut self, key: K, new_items: ITEMS)
+            where
+                ITEMS: AsRef<[V]>, // Accepts &[V], [V; LEN], Vec<V>, or other AsRef<[V]> types
+                H: Default,
+            {
+                self.add_items_with_header(key, H::default(), new_items)
+            }
+
+            /// Creates a new slice with a header and all items from an iterable of owned or borrowed V items.
+            /// Will panic if the capacity of [u32::MAX] items is reached.
+            pub fn add_items_with_header<ITEMS>(&mut self, key: K, header: H, new_items: ITEMS)
+            where
+                ITEMS: AsRef<[V]>,
+            {
+                let range = self.push_items(new_items.as_ref());
+                self.insert_entry(key, SliceEntry::new(range, header));
+            }
+
+            /// Makes the key point to the same items as an existing slice, without copying them.
+            /// The alias starts with a copy of the slice's header. Returns false if the source key is invalid.
+            pub fn add_alias(&mut self, source: K, key: K) -> bool
+            where
+                H: Clone,
+            {
+                let Some(entry) = self.alias_entry(source, None) else {
+                    return false;
+                };
+                self.insert_entry(key, entry);
+                true
+            }
+
+            /// Makes the key point to a sub-range of an existing slice, without copying its items.
+            /// The range is relative to the start of the slice. Returns false if the source key is
+            /// invalid or the range is out of bounds.
+            pub fn add_sub_alias(&mut self, source: K, key: K, range: Range<u32>) -> bool
+            where
+                H: Clone,
+            {
+                let Some(entry) = self.alias_entry(source, Some(range)) else {
+                    return false;
+                };
+                self.insert_entry(key, entry);
+                true
+            }
+
+            /// Splits a slice in two adjacent slices, without moving any items. The slice keeps the
+            /// items before the offset, and the new key points to the rest, starting with a copy of
+            /// the header. Returns false if the key is invalid, the new key is already present or the
+            /// offset is out of bounds.
+            pub fn split_slice(&mut self, key: K, at: u32, new_key: K) -> bool
+            where
+                H: Clone,
+            {
+                if self.contains_key(new_key.clone()) {
+                    return false;
+                }
+                let Some(entry) = self.split_entry(key, at) else {
+                    return false;
+                };
+                self.insert_entry(new_key, entry);
+                true
+            }
+
+            // Inserts a slice with this key, keeping the item index up to date.
+            // A replaced slice releases its items, unless they are still shared by aliases.
+            fn insert_entry(&mut self, key: K, mut entry: SliceEntry<H>) {
+                self.stamp_entry(&mut entry);
+                let range = entry.range.clone();
+                let replaced = self.slices.insert(key.clone(), entry);
+                let released = replaced
+                    .as_ref()
+                    .map(|replaced| (replaced.block.clone(), replaced.block_id));
+                self.index_insert(key.clone(), range, replaced);
+                if let Some((block, block_id)) = released {
+                    self.release_block(block, block_id);
+                }
+                self.forget_stale(&key);
+            }
+        }
+    };
+}
+
+/// SliceMap that uses [slotmap::SecondaryMap] for range storage
+pub type SecSliceMap<K, V, H = ()> = SliceMap<K, V, SecondaryMap<K, SliceEntry<H>>, H>;
+
+impl_keyed_slice_map!(SecSliceMap, Key);
+
 /// SliceMap that uses [slotmap::SparseSecondaryMap] for range storage
-pub type SparseSliceMap<K, V> = SliceMap<K, V, SparseSecondaryMap<K, Range<u32>>>;
+pub type SparseSliceMap<K, V, H = ()> = SliceMap<K, V, SparseSecondaryMap<K, SliceEntry<H>>, H>;
+
+impl_keyed_slice_map!(SparseSliceMap, Key);
+
+/// SliceMap that uses [std::collections::HashMap] for range storage, so any hashable type can be a key.
+pub type HashSliceMap<K, V, H = ()> = SliceMap<K, V, HashMap<K, SliceEntry<H>>, H>;
+
+impl_keyed_slice_map!(HashSliceMap, Clone + Eq + Hash);
+
+/// SliceMap that uses [alloc::collections::BTreeMap] for range storage, so any ordered type can be a key.
+/// [BTreeSliceMap::range] iterates the slices in key order.
+pub type BTreeSliceMap<K, V, H = ()> = SliceMap<K, V, BTreeMap<K, SliceEntry<H>>, H>;
+
+impl_keyed_slice_map!(BTreeSliceMap, Clone + Ord);
 
-impl<K, V> SparseSliceMap<K, V>
+impl<K, V, H> BTreeSliceMap<K, V, H>
 where
-    K: Key,
-    V: Clone, // Clone is required to handle &V inputs
+    K: Clone + Ord,
 {
-    /// Creates a new slice with all items from an iterable of owned or borrowed V items.
-    /// Accepts arrays, slices, or any other AsRef<[V]> type.
-    /// Will panic if the capacity of [u32::MAX] items is reached.
-    pub fn add_items<ITEMS>(&mut self, key: K, new_items: ITEMS)
+    /// Returns an iterator for the slices whose keys are within the range, along with their keys,
+    /// in key order.
+    pub fn range<R>(&self, range: R) -> impl DoubleEndedIterator<Item = (K, &[V])> + '_
     where
-        ITEMS: AsRef<[V]>, // Accepts &[V], [V; LEN], Vec<V>, or other AsRef<[V]> types
+        R: RangeBounds<K>,
     {
-        let start: u32 = self.items.len().try_into().unwrap();
-
-        // Extend items with the cloned elements from the input slice
-        self.items.extend(new_items.as_ref().iter().cloned());
-
-        let end: u32 = self.items.len().try_into().unwrap();
-        self.slices.insert(key, start..end);
+        self.slices.range(range).map(|(key, entry)| {
+            let range = entry.range.start as usize..entry.range.end as usize;
+            (key.clone(), &self.items[range])
+        })
     }
 }
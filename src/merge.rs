@@ -0,0 +1,293 @@
+use core::ops::Range;
+use slotmap::{Key, SecondaryMap};
+
+use alloc::vec::Vec;
+
+use crate::{SecSliceMap, SliceEntry, SliceMap, SliceMapError, SliceStorage, SlotSliceMap};
+
+/// Maps the keys of a SliceMap that was appended to another one to their new keys.
+#[derive(Debug, Clone)]
+pub struct KeyRemap<K>
+where
+    K: Key,
+{
+    pub(crate) keys: SecondaryMap<K, K>, // Old key to new key
+}
+
+impl<K> KeyRemap<K>
+where
+    K: Key,
+{
+    /// Returns the new key for an old key.
+    pub fn get(&self, old: K) -> Option<K> {
+        self.keys.get(old).copied()
+    }
+
+    /// Returns an iterator of old and new key pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (K, K)> + '_ {
+        self.keys.iter().map(|(old, new)| (old, *new))
+    }
+
+    /// How many keys were remapped.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// True if no keys were remapped.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl<K, V, S, H> SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
+{
+    // Moves all items from the other map to the end of this one, returning the offset that
+    // must be added to the other map's ranges. Leaves the other map with no items.
+    fn append_items(&mut self, other: &mut Self) -> u32 {
+        assert!(
+            self.items.len() + other.items.len() <= u32::MAX as usize,
+            "Capacity of u32::MAX items reached"
+        );
+        let offset = self.items.len() as u32;
+        self.items.append(&mut other.items);
+        self.mark_dirty(offset..self.items.len() as u32);
+        offset
+    }
+
+    // Reserves the other map's block ids past all of this map's ids, returning the base that
+    // must be added to them. Every block id is at most its map's version.
+    fn reserve_block_ids(&mut self, other: &Self) -> u64 {
+        let base = self.version;
+        self.version += other.version;
+        base
+    }
+}
+
+// Shifts an appended entry to its new position, moving its block id past the reserved base.
+fn shift_entry<H>(entry: &mut SliceEntry<H>, offset: u32, id_base: u64, version: u64) {
+    entry.range = entry.range.start + offset..entry.range.end + offset;
+    entry.block = entry.block.start + offset..entry.block.end + offset;
+    entry.block_id += id_base;
+    entry.version = version;
+}
+
+impl<K, V, H> SlotSliceMap<K, V, H>
+where
+    K: Key,
+{
+    /// Moves all slices and items from the other map into this one, leaving it empty.
+    /// The items are moved in a single pass, and their slices get new keys in this map,
+    /// which are returned as a mapping from the other map's keys. Aliases are preserved.
+    /// Will panic if the capacity of [u32::MAX] items is reached.
+    pub fn append(&mut self, other: &mut Self) -> KeyRemap<K> {
+        let offset = self.append_items(other);
+        let id_base = self.reserve_block_ids(other);
+        let version = self.next_version();
+        let mut remap = KeyRemap {
+            keys: SecondaryMap::with_capacity(other.slices.len()),
+        };
+        for (old, mut entry) in other.slices.drain() {
+            shift_entry(&mut entry, offset, id_base, version);
+            remap.keys.insert(old, self.slices.insert(entry));
+        }
+        // Appended ranges start after all existing ones, so the index stays sorted
+        for (range, old) in other.index.drain(..) {
+            self.index
+                .push((range.start + offset..range.end + offset, remap.keys[old]));
+        }
+        other.clear();
+        remap
+    }
+}
+
+impl<K, V, H> SecSliceMap<K, V, H>
+where
+    K: Key,
+{
+    /// Moves all slices and items from the other map into this one, leaving it empty.
+    /// The items are moved in a single pass, and all slices keep their keys. Aliases are preserved.
+    /// Returns an error without changing either map if any key is present in both maps.
+    /// Will panic if the capacity of [u32::MAX] items is reached.
+    pub fn append(&mut self, other: &mut Self) -> Result<(), SliceMapError<K>> {
+        if let Some(key) = other
+            .slices
+            .keys()
+            .find(|&key| self.slices.contains_key(key))
+        {
+            return Err(SliceMapError::KeyCollision(key));
+        }
+        let offset = self.append_items(other);
+        let id_base = self.reserve_block_ids(other);
+        let version = self.next_version();
+        for (key, mut entry) in other.slices.drain() {
+            shift_entry(&mut entry, offset, id_base, version);
+            self.forget_stale(&key);
+            self.slices.insert(key, entry);
+        }
+        // Appended ranges start after all existing ones, so the index stays sorted
+        for (range, key) in other.index.drain(..) {
+            self.index
+                .push((range.start + offset..range.end + offset, key));
+        }
+        other.clear();
+        Ok(())
+    }
+}
+
+// Where a block of items ends up when a map is split.
+struct BlockSplit {
+    block: Range<u32>,
+    block_id: u64,
+    keep: bool,       // Some slice in the block stays in the original map
+    split: bool,      // Some slice in the block moves to the new map
+    kept_start: u32,  // New start in the original map
+    split_start: u32, // Start in the new map
+}
+
+impl<K, V, S, H> SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    V: Clone, // Clone is required when aliases end up on both sides of a split
+    H: Clone,
+    S: SliceStorage<K, SliceEntry<H>> + Default + Clone,
+{
+    /// Moves the slices with these keys into a new map, which keeps the same keys.
+    /// Both maps are compacted in a single pass over the items, so all following items in this
+    /// map "shift" to occupy the removed space. Invalid keys are ignored.
+    /// Items shared by aliases that end up in both maps are copied into both.
+    pub fn split_off<I>(&mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+    {
+        self.split_selected(keys.into_iter().collect())
+    }
+
+    /// Moves the slices for which the predicate returns true into a new map, which keeps the
+    /// same keys. Both maps are compacted in a single pass over the items, like [SliceMap::split_off].
+    pub fn partition_slices<F>(&mut self, mut predicate: F) -> Self
+    where
+        F: FnMut(K, &H, &[V]) -> bool,
+    {
+        let selected = self
+            .iter_with_headers()
+            .filter_map(|(key, header, slice)| predicate(key.clone(), header, slice).then_some(key))
+            .collect();
+        self.split_selected(selected)
+    }
+
+    // Moves the selected slices into a new map, compacting both in a single pass.
+    // Each slice ends up in the storage of only one map, which tells which side it belongs to.
+    fn split_selected(&mut self, selected: Vec<K>) -> Self {
+        let mut other = Self::new();
+        other.slices = self.slices.clone();
+        if self.dirty.is_some() {
+            other.dirty = Some(Vec::new());
+        }
+        for key in &selected {
+            self.slices.remove(key);
+        }
+
+        // Find which side each block of items goes to
+        let mut blocks: Vec<BlockSplit> = Vec::new();
+        for (key, entry) in other.slices.iter() {
+            let keep = self.slices.get(&key).is_some();
+            blocks.push(BlockSplit {
+                block: entry.block.clone(),
+                block_id: entry.block_id,
+                keep,
+                split: !keep,
+                kept_start: 0,
+                split_start: 0,
+            });
+        }
+        blocks.sort_unstable_by_key(|split| (split.block.start, split.block.end, split.block_id));
+        blocks.dedup_by(|next, prev| {
+            let same = next.block_id == prev.block_id;
+            if same {
+                prev.keep |= next.keep;
+                prev.split |= next.split;
+            }
+            same
+        });
+
+        // Move each block's items in a single pass, dropping items outside of any block
+        let mut items = core::mem::take(&mut self.items).into_iter();
+        let mut position = 0;
+        let mut changed_from = None;
+        for split in &mut blocks {
+            let gap = split.block.start as usize - position;
+            items.by_ref().take(gap).for_each(drop);
+            let len = (split.block.end - split.block.start) as usize;
+            position = split.block.end as usize;
+            split.kept_start = self.items.len() as u32;
+            split.split_start = other.items.len() as u32;
+            if !split.keep || split.kept_start != split.block.start {
+                changed_from.get_or_insert(split.kept_start);
+            }
+            match (split.keep, split.split) {
+                (true, true) => {
+                    let start = self.items.len();
+                    self.items.extend(items.by_ref().take(len));
+                    other.items.extend_from_slice(&self.items[start..]);
+                }
+                (true, false) => self.items.extend(items.by_ref().take(len)),
+                _ => other.items.extend(items.by_ref().take(len)),
+            }
+        }
+        if changed_from.is_none() && items.len() > 0 {
+            changed_from = Some(self.items.len() as u32);
+        }
+        drop(items);
+
+        // Update the ranges on both sides, removing the kept slices from the new map
+        for (key, _) in self.slices.iter() {
+            other.slices.remove(&key);
+        }
+        let find = |blocks: &[BlockSplit], entry: &SliceEntry<H>| {
+            let i = blocks.partition_point(|split| {
+                (split.block.start, split.block.end, split.block_id)
+                    < (entry.block.start, entry.block.end, entry.block_id)
+            });
+            (blocks[i].kept_start, blocks[i].split_start)
+        };
+        for entry in self.slices.values_mut() {
+            let (start, _) = find(&blocks, entry);
+            relocate(entry, start);
+        }
+        other.version = self.version; // Keeps the copied block ids unique in the new map
+        let version = other.next_version();
+        for entry in other.slices.values_mut() {
+            let (_, start) = find(&blocks, entry);
+            relocate(entry, start);
+            entry.version = version;
+        }
+
+        // Both indices keep the original order, since blocks keep their relative order
+        let index = core::mem::take(&mut self.index);
+        for (_, key) in index {
+            if let Some(entry) = self.slices.get(&key) {
+                self.index.push((entry.range.clone(), key));
+            } else if let Some(entry) = other.slices.get(&key) {
+                other.index.push((entry.range.clone(), key));
+            }
+        }
+
+        if let Some(start) = changed_from {
+            self.mark_dirty(start..self.items.len() as u32);
+        }
+        other.mark_dirty(0..other.items.len() as u32);
+        self.next_version();
+        other
+    }
+}
+
+// Moves an entry's block to a new start, keeping its range at the same offset within the block.
+pub(crate) fn relocate<H>(entry: &mut SliceEntry<H>, start: u32) {
+    let offset = entry.range.start - entry.block.start;
+    let len = entry.range.end - entry.range.start;
+    entry.block = start..start + (entry.block.end - entry.block.start);
+    entry.range = start + offset..start + offset + len;
+}
@@ -0,0 +1,273 @@
+use core::ops::Range;
+use slotmap::{Key, SlotMap};
+
+use alloc::vec::Vec;
+
+/// A group of slices, which occupies a contiguous range of items.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct Group<K> {
+    pub(crate) range: Range<u32>, // Range of all items in the group
+    pub(crate) slices: Vec<K>,    // Keys of the group's slices, in item order
+}
+
+/// A slice inside a group.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct SubSlice<G> {
+    pub(crate) group: G,
+    pub(crate) range: Range<u32>, // Relative to the start of the group
+}
+
+/// A two-level SliceMap, where each group is a contiguous run of slices and each slice is
+/// a contiguous run of items. A whole group can be accessed as a single slice of items,
+/// or slice by slice. G is the group key type, and K is the slice key type.
+#[derive(Default, Clone)]
+pub struct NestedSliceMap<G, K, V>
+where
+    G: Key,
+    K: Key,
+{
+    pub(crate) items: Vec<V>,
+    pub(crate) groups: SlotMap<G, Group<K>>,
+    pub(crate) slices: SlotMap<K, SubSlice<G>>,
+}
+
+impl<G, K, V> NestedSliceMap<G, K, V>
+where
+    G: Key,
+    K: Key,
+{
+    /// Returns a new, empty NestedSliceMap.
+    pub fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            groups: SlotMap::with_key(),
+            slices: SlotMap::with_key(),
+        }
+    }
+
+    /// Returns a new NestedSliceMap with the specified initial item capacity.
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            items: Vec::with_capacity(cap),
+            groups: SlotMap::with_key(),
+            slices: SlotMap::with_key(),
+        }
+    }
+
+    /// Clears all groups, slices and items.
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.groups.clear();
+        self.slices.clear();
+    }
+
+    /// Returns a slice with all items in all groups.
+    pub fn items(&self) -> &[V] {
+        &self.items
+    }
+
+    /// How many items are contained in all groups.
+    pub fn items_len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// True if no items
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// How many groups are contained in the NestedSliceMap.
+    pub fn groups_len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// How many slices are contained in all groups.
+    pub fn slices_len(&self) -> usize {
+        self.slices.len()
+    }
+
+    /// Returns all items in a group as a single slice.
+    pub fn get_group(&self, group: G) -> Option<&[V]> {
+        let range = &self.groups.get(group)?.range;
+        self.items.get(range.start as usize..range.end as usize)
+    }
+
+    /// Returns all items in a group as a single mutable slice.
+    pub fn get_group_mut(&mut self, group: G) -> Option<&mut [V]> {
+        let range = &self.groups.get(group)?.range;
+        self.items.get_mut(range.start as usize..range.end as usize)
+    }
+
+    /// Returns the keys of all slices in a group, in item order.
+    pub fn group_keys(&self, group: G) -> Option<&[K]> {
+        Some(&self.groups.get(group)?.slices)
+    }
+
+    /// Returns the group that contains a slice.
+    pub fn group_of(&self, key: K) -> Option<G> {
+        Some(self.slices.get(key)?.group)
+    }
+
+    /// Returns a slice within a group.
+    pub fn get_slice(&self, key: K) -> Option<&[V]> {
+        let range = self.absolute_range(key)?;
+        self.items.get(range)
+    }
+
+    /// Returns a mutable slice within a group.
+    pub fn get_slice_mut(&mut self, key: K) -> Option<&mut [V]> {
+        let range = self.absolute_range(key)?;
+        self.items.get_mut(range)
+    }
+
+    /// Returns an iterator for all groups, each one as a single slice of items.
+    pub fn iter_groups(&self) -> impl Iterator<Item = (G, &[V])> {
+        self.groups.iter().map(|(key, group)| {
+            let range = group.range.start as usize..group.range.end as usize;
+            (key, &self.items[range])
+        })
+    }
+
+    /// Returns an iterator for the slices in a group along with their keys, in item order.
+    pub fn iter_group_slices(&self, group: G) -> Option<impl Iterator<Item = (K, &[V])>> {
+        let group = self.groups.get(group)?;
+        let group_items = &self.items[group.range.start as usize..group.range.end as usize];
+        Some(group.slices.iter().map(move |&key| {
+            let range = &self.slices[key].range;
+            (key, &group_items[range.start as usize..range.end as usize])
+        }))
+    }
+
+    /// Removes a group and all of its slices in a single pass, returning the removed item range.
+    /// Warning: Will cause all items to "shift" to occupy the removed space.
+    pub fn remove_group(&mut self, group: G) -> Option<Range<u32>> {
+        let removed = self.groups.remove(group)?;
+        for key in &removed.slices {
+            self.slices.remove(*key);
+        }
+        let removed = removed.range;
+        self.items
+            .drain(removed.start as usize..removed.end as usize);
+
+        // Adjust the ranges of all subsequent groups
+        let offset = removed.end - removed.start;
+        for other in self.groups.values_mut() {
+            if other.range.start >= removed.end {
+                other.range.start -= offset;
+                other.range.end -= offset;
+            }
+        }
+        Some(removed)
+    }
+
+    /// Removes a single slice from its group, returning the removed item range.
+    /// Warning: Will cause all items to "shift" to occupy the removed space.
+    pub fn remove_slice(&mut self, key: K) -> Option<Range<u32>> {
+        let removed = self.absolute_range(key)?;
+        let SubSlice {
+            group,
+            range: local,
+        } = self.slices.remove(key)?;
+        self.items.drain(removed.clone());
+
+        // Adjust the rest of the group's slices, then the group itself
+        let offset = local.end - local.start;
+        let owner = &mut self.groups[group];
+        owner.slices.retain(|&other| other != key);
+        for other in &owner.slices {
+            let range = &mut self.slices[*other].range;
+            if range.start >= local.end {
+                range.start -= offset;
+                range.end -= offset;
+            }
+        }
+        owner.range.end -= offset;
+
+        let removed = removed.start as u32..removed.end as u32;
+        for (other_key, other) in self.groups.iter_mut() {
+            if other_key != group && other.range.start >= removed.end {
+                other.range.start -= offset;
+                other.range.end -= offset;
+            }
+        }
+        Some(removed)
+    }
+
+    // Converts a slice's range within its group to a range within all items.
+    fn absolute_range(&self, key: K) -> Option<Range<usize>> {
+        let slice = self.slices.get(key)?;
+        let start = self.groups.get(slice.group)?.range.start;
+        Some((start + slice.range.start) as usize..(start + slice.range.end) as usize)
+    }
+}
+
+impl<G, K, V> NestedSliceMap<G, K, V>
+where
+    G: Key,
+    K: Key,
+    V: Clone, // Clone is required to handle &V inputs
+{
+    /// Creates a new group containing one new slice for each item group in the iterator.
+    /// All items in the group will be contiguous.
+    /// Will panic if the capacity of [u32::MAX] items is reached.
+    pub fn add_group<I, ITEMS>(&mut self, slices: I) -> G
+    where
+        I: IntoIterator<Item = ITEMS>,
+        ITEMS: AsRef<[V]>,
+    {
+        let start: u32 = self.items.len().try_into().unwrap();
+        let group = self.groups.insert(Group {
+            range: start..start,
+            slices: Vec::new(),
+        });
+        for new_items in slices {
+            let local_start = self.items.len() as u32 - start;
+            self.items.extend(new_items.as_ref().iter().cloned());
+            let local_end: u32 = (self.items.len() - start as usize).try_into().unwrap();
+            let key = self.slices.insert(SubSlice {
+                group,
+                range: local_start..local_end,
+            });
+            self.groups[group].slices.push(key);
+        }
+        self.groups[group].range.end = self.items.len().try_into().unwrap();
+        group
+    }
+
+    /// Appends a new slice to the end of an existing group, returning its key.
+    /// Warning: Will cause all items in subsequent groups to "shift" to make room for the new items.
+    /// Will panic if the capacity of [u32::MAX] items is reached.
+    pub fn push_slice<ITEMS>(&mut self, group: G, new_items: ITEMS) -> Option<K>
+    where
+        ITEMS: AsRef<[V]>,
+    {
+        let new_items = new_items.as_ref();
+        let offset: u32 = new_items.len().try_into().unwrap();
+        let range = self.groups.get(group)?.range.clone();
+        assert!(
+            self.items.len() + new_items.len() <= u32::MAX as usize,
+            "Capacity of u32::MAX items reached"
+        );
+
+        let at = range.end as usize;
+        self.items.splice(at..at, new_items.iter().cloned());
+
+        // Make room by shifting all subsequent groups
+        for (other_key, other) in self.groups.iter_mut() {
+            if other_key != group && other.range.start >= range.end {
+                other.range.start += offset;
+                other.range.end += offset;
+            }
+        }
+
+        let local_start = range.end - range.start;
+        let key = self.slices.insert(SubSlice {
+            group,
+            range: local_start..local_start + offset,
+        });
+        let owner = &mut self.groups[group];
+        owner.range.end += offset;
+        owner.slices.push(key);
+        Some(key)
+    }
+}
@@ -0,0 +1,48 @@
+use core::ops::Range;
+use rayon::prelude::*;
+
+use alloc::vec::Vec;
+
+use crate::{disjoint_order, split_ranges_mut, SliceEntry, SliceMap, SliceStorage};
+
+/// Parallel iteration, available with the "rayon" feature.
+impl<K, V, S, H> SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
+{
+    /// Returns a parallel iterator for slices of items, in the order their items are laid out,
+    /// as with [SliceMap::slice_of_item]. Aliases each yield their own slice.
+    pub fn par_iter_slices(&self) -> impl IndexedParallelIterator<Item = &[V]>
+    where
+        K: Sync,
+        V: Sync,
+    {
+        let items = &self.items;
+        self.index
+            .par_iter()
+            .map(move |(range, _)| &items[range.start as usize..range.end as usize])
+    }
+
+    /// Returns a parallel iterator for mutable slices of items, in the same order as [SliceMap::iter_slices].
+    /// Unless they are aliases, slices never overlap, so each one can be safely modified from a
+    /// different thread. Returns None only if some non-empty slices share items through aliases.
+    pub fn par_iter_slices_mut(&mut self) -> Option<impl IndexedParallelIterator<Item = &mut [V]>>
+    where
+        V: Send,
+    {
+        let ranges: Vec<Range<u32>> = self.index.iter().map(|(range, _)| range.clone()).collect();
+        let order = disjoint_order(&ranges)?;
+        self.mark_all_written();
+        Some(split_ranges_mut(&mut self.items, &ranges, &order).into_par_iter())
+    }
+
+    /// Returns a parallel iterator for each individual item.
+    pub fn par_iter_items_mut(&mut self) -> impl IndexedParallelIterator<Item = &mut V>
+    where
+        V: Send,
+    {
+        self.mark_all_written();
+        self.items.par_iter_mut()
+    }
+}
@@ -0,0 +1,182 @@
+use core::ops::Range;
+use slotmap::{Key, SlotMap};
+
+use alloc::vec::Vec;
+
+use crate::merge::relocate;
+use crate::{SecSliceMap, SliceEntry, SliceMap, SliceStorage};
+
+impl<K, V, S, H> SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + Default,
+{
+    /// Removes the slices for which the predicate returns false, returning how many were removed.
+    /// The items are compacted in a single pass, so all following items "shift" to occupy the
+    /// removed space. Items shared by aliases are only removed along with the last alias.
+    pub fn retain_slices<F>(&mut self, mut predicate: F) -> usize
+    where
+        F: FnMut(K, &H, &[V]) -> bool,
+    {
+        let removed: Vec<K> = self
+            .iter_with_headers()
+            .filter_map(|(key, header, slice)| {
+                (!predicate(key.clone(), header, slice)).then_some(key)
+            })
+            .collect();
+        self.remove_keys(removed).len()
+    }
+
+    // Removes the slices with these keys, compacting the items in a single pass.
+    // Returns the removed keys along with their entries. Invalid keys are ignored.
+    pub(crate) fn remove_keys(&mut self, keys: Vec<K>) -> Vec<(K, SliceEntry<H>)> {
+        let removed: Vec<_> = keys
+            .into_iter()
+            .filter_map(|key| {
+                let entry = self.slices.remove(&key)?;
+                Some((key, entry))
+            })
+            .collect();
+        if removed.is_empty() {
+            return removed;
+        }
+        self.next_version();
+
+        // Blocks still used by any slice, in item order
+        let mut blocks: Vec<(Range<u32>, u64)> = self
+            .slices
+            .values()
+            .map(|entry| (entry.block.clone(), entry.block_id))
+            .collect();
+        blocks.sort_unstable_by_key(|(block, id)| (block.start, block.end, *id));
+        blocks.dedup_by_key(|(_, id)| *id);
+
+        // Drop every item outside of those blocks
+        let mut position = 0;
+        let mut next = blocks.iter().peekable();
+        self.items.retain(|_| {
+            while next.next_if(|(block, _)| block.end <= position).is_some() {}
+            let keep = next
+                .peek()
+                .is_some_and(|(block, _)| block.start <= position);
+            position += 1;
+            keep
+        });
+
+        // Move each block to its new start, which only decreases
+        let mut starts = Vec::with_capacity(blocks.len());
+        let mut start = 0;
+        let mut changed_from = None;
+        for (block, _) in &blocks {
+            if start != block.start {
+                changed_from.get_or_insert(start);
+            }
+            starts.push(start);
+            start += block.end - block.start;
+        }
+        for entry in self.slices.values_mut() {
+            let i = blocks.partition_point(|(block, id)| {
+                (block.start, block.end, *id) < (entry.block.start, entry.block.end, entry.block_id)
+            });
+            relocate(entry, starts[i]);
+        }
+
+        // The index keeps its order, since blocks keep their relative order
+        let index = core::mem::take(&mut self.index);
+        for (_, key) in index {
+            if let Some(entry) = self.slices.get(&key) {
+                self.index.push((entry.range.clone(), key));
+            }
+        }
+
+        if let Some(start) = changed_from {
+            self.mark_dirty(start..self.items.len() as u32);
+        }
+        removed
+    }
+
+    // Flags access with a key whose slice was removed by retain_alive or sync_with,
+    // while stale key checks are enabled. Only called when the key wasn't found.
+    #[cfg(debug_assertions)]
+    pub(crate) fn check_stale(&self, key: &K) {
+        assert!(
+            self.stale
+                .as_ref()
+                .is_none_or(|stale| !stale.contains(key)),
+            "SliceMap accessed with a stale key, whose slice was removed by retain_alive or sync_with"
+        );
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline(always)]
+    pub(crate) fn check_stale(&self, _key: &K) {}
+
+    // Makes a key valid again once a slice is added with it.
+    #[cfg(debug_assertions)]
+    pub(crate) fn forget_stale(&mut self, key: &K) {
+        if let Some(stale) = &mut self.stale {
+            stale.retain(|other| other != key);
+        }
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline(always)]
+    pub(crate) fn forget_stale(&mut self, _key: &K) {}
+}
+
+impl<K, V, H> SecSliceMap<K, V, H>
+where
+    K: Key,
+{
+    /// Removes the slices whose keys are no longer present in the primary SlotMap that the keys
+    /// come from, returning how many were removed. The items are compacted in a single pass.
+    pub fn retain_alive<P>(&mut self, primary: &SlotMap<K, P>) -> usize {
+        self.remove_stale(|key| primary.contains_key(key))
+    }
+
+    /// Removes the slices whose keys are no longer present in the primary SliceMap that the keys
+    /// come from, returning how many were removed. The items are compacted in a single pass.
+    pub fn sync_with<W, S, HS>(&mut self, primary: &SliceMap<K, W, S, HS>) -> usize
+    where
+        S: SliceStorage<K, SliceEntry<HS>> + Default,
+    {
+        self.remove_stale(|key| primary.contains_key(key))
+    }
+
+    /// Enables or disables stale key checks. While enabled, the keys of slices removed by
+    /// [SecSliceMap::retain_alive] or [SecSliceMap::sync_with] are remembered, and accessing
+    /// their slices afterwards triggers a debug assertion, instead of silently returning None.
+    /// Adding a slice with a stale key makes it valid again.
+    /// Only has an effect in debug builds, where the stale keys are kept in a plain list, so
+    /// each check takes O(stale keys). Disabling it forgets all stale keys.
+    pub fn set_stale_key_checks(&mut self, enabled: bool) {
+        #[cfg(debug_assertions)]
+        if enabled != self.stale.is_some() {
+            self.stale = enabled.then(Vec::new);
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = enabled;
+    }
+
+    /// True if stale key checks are enabled. Always false in release builds.
+    pub fn is_checking_stale_keys(&self) -> bool {
+        #[cfg(debug_assertions)]
+        return self.stale.is_some();
+        #[cfg(not(debug_assertions))]
+        false
+    }
+
+    // Removes the slices whose keys are not alive, remembering them if checking stale keys.
+    fn remove_stale<F>(&mut self, alive: F) -> usize
+    where
+        F: Fn(K) -> bool,
+    {
+        let keys = self.slices.keys().filter(|key| !alive(*key)).collect();
+        let removed = self.remove_keys(keys);
+        #[cfg(debug_assertions)]
+        if let Some(stale) = &mut self.stale {
+            stale.extend(removed.iter().map(|(key, _)| *key));
+        }
+        removed.len()
+    }
+}
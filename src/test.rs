@@ -1,5 +1,5 @@
-use crate::SlotSliceMap;
-use slotmap::new_key_type;
+use crate::{SecSliceMap, SlotSliceMap};
+use slotmap::{new_key_type, SlotMap};
 
 extern crate alloc;
 use alloc::vec::Vec;
@@ -13,6 +13,11 @@ new_key_type! {
 // }
 
 #[test]
+#[allow(
+    clippy::explicit_counter_loop,
+    clippy::unnecessary_cast,
+    clippy::useless_conversion
+)]
 fn test_basic() {
     let mut slicemap = SlotSliceMap::<TestKey, i32>::new();
     let max_slices = 10;
@@ -30,6 +35,7 @@ fn test_basic() {
 }
 
 #[test]
+#[allow(clippy::drop_non_drop, clippy::explicit_counter_loop)]
 fn test_remove() {
     let mut slicemap = SlotSliceMap::<TestKey, i32>::new();
 
@@ -91,3 +97,1367 @@ fn non_default_values() {
     assert_eq!(slices.items_len(), 4);
     assert_eq!(slices.slices_len(), 2);
 }
+
+#[test]
+fn collect_extend_and_into_iter() {
+    let mut slicemap: SlotSliceMap<TestKey, i32> =
+        [vec![1, 2, 3], vec![4], vec![5, 6]].into_iter().collect();
+    assert_eq!(slicemap.slices_len(), 3);
+    assert_eq!(slicemap.items_len(), 6);
+
+    slicemap.extend([[7, 8], [9, 10]]);
+    assert_eq!(slicemap.slices_len(), 5);
+    assert_eq!(slicemap.items(), &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+
+    let keys: Vec<TestKey> = (&slicemap).into_iter().map(|(key, _)| key).collect();
+    slicemap.remove_slice(keys[1]);
+
+    let consumed: Vec<(TestKey, Vec<i32>)> = slicemap.into_iter().collect();
+    assert_eq!(
+        consumed,
+        vec![
+            (keys[0], vec![1, 2, 3]),
+            (keys[2], vec![5, 6]),
+            (keys[3], vec![7, 8]),
+            (keys[4], vec![9, 10]),
+        ]
+    );
+}
+
+#[test]
+fn index_and_many_mut() {
+    let mut slicemap = SlotSliceMap::<TestKey, i32>::new();
+    let a = slicemap.add_items([1, 2, 3]);
+    let b = slicemap.add_items([4, 5]);
+    let c = slicemap.add_items([6]);
+
+    assert_eq!(slicemap[a], [1, 2, 3]);
+    slicemap[b][1] = 50;
+    assert_eq!(slicemap[b], [4, 50]);
+
+    slicemap.remove_slice(b);
+    assert!(slicemap.contains_key(a));
+    assert!(!slicemap.contains_key(b));
+
+    let [slice_c, slice_a] = slicemap.get_many_mut([c, a]).unwrap();
+    slice_c[0] = 60;
+    slice_a[0] = 10;
+    assert_eq!(slicemap.items(), &[10, 2, 3, 60]);
+
+    assert!(slicemap.get_many_mut([a, a]).is_none());
+    assert!(slicemap.get_many_mut([a, b]).is_none());
+
+    // An empty slice starting where another one starts doesn't overlap it, in either order
+    let empty = slicemap.add_items([]);
+    let d = slicemap.add_items([7, 8]);
+    assert!(slicemap.get_many_mut([empty, d]).is_some());
+    let [slice_d, slice_empty] = slicemap.get_many_mut([d, empty]).unwrap();
+    slice_d[0] = 70;
+    assert!(slice_empty.is_empty());
+    assert_eq!(slicemap[d], [70, 8]);
+}
+
+#[test]
+#[should_panic]
+fn index_invalid_key() {
+    let mut slicemap = SlotSliceMap::<TestKey, i32>::new();
+    let a = slicemap.add_items([1, 2, 3]);
+    slicemap.remove_slice(a);
+    let _ = &slicemap[a];
+}
+
+#[test]
+fn equality_hash_and_debug() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    fn hash_of<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let mut primary = SlotMap::<TestKey, ()>::with_key();
+    let k1 = primary.insert(());
+    let k2 = primary.insert(());
+
+    // Same keyed contents with different item layouts are equal
+    let mut a = SecSliceMap::<TestKey, i32>::new();
+    a.add_items(k1, [1, 2, 3]);
+    a.add_items(k2, [4, 5]);
+    let mut b = SecSliceMap::<TestKey, i32>::new();
+    b.add_items(k2, [4, 5]);
+    b.add_items(k1, [1, 2, 3]);
+    assert_ne!(a.items(), b.items());
+    assert_eq!(a, b);
+    assert_eq!(hash_of(&a), hash_of(&b));
+
+    b[k2][0] = 40;
+    assert_ne!(a, b);
+    b.remove_slice(k2);
+    assert_ne!(a, b);
+    a.remove_slice(k2);
+    assert_eq!(a, b);
+
+    let mut c = SlotSliceMap::<TestKey, i32>::new();
+    let j1 = c.add_items([1, 2]);
+    let j2 = c.add_items([3]);
+    let d = c.clone();
+    assert_eq!(c, d);
+    assert_eq!(hash_of(&c), hash_of(&d));
+    assert_eq!(
+        format!("{:?}", c),
+        format!("{{{:?}: [1, 2], {:?}: [3]}}", j1, j2)
+    );
+
+    let mut e = crate::BTreeSliceMap::<u64, i32>::new();
+    e.add_items(2, [3]);
+    e.add_items(1, [1, 2]);
+    let mut f = crate::BTreeSliceMap::<u64, i32>::new();
+    f.add_items(1, [1, 2]);
+    f.add_items(2, [3]);
+    assert_eq!(e, f);
+    assert_eq!(hash_of(&e), hash_of(&f));
+
+    let hop: crate::HopSliceMap<TestKey, i32> = [[1, 2], [3, 4]].into_iter().collect();
+    assert_eq!(hash_of(&hop), hash_of(&hop.clone()));
+}
+
+#[test]
+fn double_ended_and_exact_size() {
+    let mut slicemap = SlotSliceMap::<TestKey, i32>::new();
+    let a = slicemap.add_items([1, 2, 3]);
+    let b = slicemap.add_items([4, 5]);
+    let c = slicemap.add_items([6]);
+
+    let slices = slicemap.iter_slices();
+    assert_eq!(slices.len(), 3);
+    assert_eq!(slices.size_hint(), (3, Some(3)));
+
+    let mut keyed = slicemap.iter_keys_and_slices();
+    assert_eq!(keyed.nth(1), Some((b, &[4, 5][..])));
+    assert_eq!(keyed.len(), 1);
+    assert_eq!(keyed.next(), Some((c, &[6][..])));
+    assert_eq!(keyed.next(), None);
+    assert_eq!(keyed.next(), None);
+
+    // Slice iterators are double-ended for every storage type
+    let reversed: Vec<&[i32]> = slicemap.iter_slices().rev().collect();
+    assert_eq!(reversed, [&[6][..], &[4, 5], &[1, 2, 3]]);
+    let mut keyed = slicemap.iter_keys_and_slices();
+    assert_eq!(keyed.next_back(), Some((c, &[6][..])));
+    assert_eq!(keyed.len(), 2);
+    assert_eq!(slicemap.iter_with_headers().rev().nth(2).unwrap().0, a);
+
+    let mut sorted = crate::BTreeSliceMap::<u32, i32>::new();
+    sorted.add_items(2, [4, 5]);
+    sorted.add_items(1, [1, 2, 3]);
+    let reversed: Vec<&[i32]> = sorted.iter_slices().rev().collect();
+    assert_eq!(reversed, [&[1, 2, 3][..], &[4, 5]]);
+
+    let mut items = slicemap.iter_items();
+    assert_eq!(items.len(), 6);
+    assert_eq!(items.next_back(), Some(&6));
+
+    let mut consumed = slicemap.into_iter();
+    assert_eq!(consumed.len(), 3);
+    assert_eq!(consumed.next_back(), Some((c, vec![6])));
+    assert_eq!(consumed.next(), Some((a, vec![1, 2, 3])));
+    assert_eq!(consumed.next_back(), Some((b, vec![4, 5])));
+    assert_eq!(consumed.next(), None);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn parallel_iteration() {
+    use rayon::prelude::*;
+
+    let mut slicemap = SlotSliceMap::<TestKey, i32>::new();
+    let a = slicemap.add_items([1, 2, 3]);
+    let b = slicemap.add_items([4, 5]);
+    let c = slicemap.add_items([6]);
+    slicemap.remove_slice(b);
+
+    let sums: Vec<i32> = slicemap
+        .par_iter_slices()
+        .map(|slice| slice.iter().sum())
+        .collect();
+    assert_eq!(sums, [6, 6]);
+
+    slicemap
+        .par_iter_slices_mut()
+        .unwrap()
+        .for_each(|slice| slice.reverse());
+    assert_eq!(slicemap[a], [3, 2, 1]);
+    assert_eq!(slicemap[c], [6]);
+
+    slicemap.par_iter_items_mut().for_each(|item| *item *= 10);
+    assert_eq!(slicemap.items(), &[30, 20, 10, 60]);
+
+    // Empty slices don't overlap the slice that starts at the same position
+    let mut shapes = SlotSliceMap::<TestKey, i32>::new();
+    let removed = shapes.add_items([0]);
+    shapes.add_items([1, 2, 3]);
+    let empty = shapes.add_items([]);
+    shapes.remove_slice(removed);
+    let d = shapes.add_items([4, 5]);
+    shapes
+        .par_iter_slices_mut()
+        .unwrap()
+        .for_each(|slice| slice.reverse());
+    assert_eq!(shapes.get_slice(empty), Some(&[][..]));
+    assert_eq!(shapes[d], [5, 4]);
+
+    // Aliases can't be mutably borrowed at once
+    slicemap.add_alias(a);
+    assert!(slicemap.par_iter_slices_mut().is_none());
+    assert_eq!(slicemap[a], [30, 20, 10]);
+}
+
+#[test]
+fn multiple_columns() {
+    use crate::SlotSliceMap3;
+
+    let mut polygons = SlotSliceMap3::<TestKey, [f32; 2], u8, i32>::new();
+    let a = polygons.add_items(
+        [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]],
+        [1, 2, 3],
+        [10, 20, 30],
+    );
+    let b = polygons.add_items(vec![[2.0, 2.0]], [4], [40]);
+    let c = polygons.add_items([[3.0, 3.0], [4.0, 4.0]], [5, 6], [50, 60]);
+    assert_eq!(polygons.items_len(), 6);
+    assert_eq!(polygons.slices_len(), 3);
+
+    polygons.remove_slice(b);
+    let (positions, colors, uvs) = polygons.items();
+    assert_eq!(positions.len(), 5);
+    assert_eq!(colors, &[1, 2, 3, 5, 6]);
+    assert_eq!(uvs, &[10, 20, 30, 50, 60]);
+
+    let (positions, colors, uvs) = polygons.get_slice(c).unwrap();
+    assert_eq!(positions, &[[3.0, 3.0], [4.0, 4.0]]);
+    assert_eq!(colors, &[5, 6]);
+    assert_eq!(uvs, &[50, 60]);
+
+    let (_, colors, _) = polygons.get_slice_mut(a).unwrap();
+    colors[0] = 100;
+    let mut slices = polygons.iter_slices();
+    assert_eq!(slices.len(), 2);
+    assert_eq!(slices.next().unwrap().1, &[100, 2, 3]);
+    assert_eq!(slices.next().unwrap().2, &[50, 60]);
+    drop(slices);
+
+    let keys: Vec<TestKey> = polygons
+        .iter_keys_and_slices()
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(keys, [a, c]);
+    polygons.remove_slice(a);
+    polygons.remove_slice(c);
+    assert!(polygons.is_empty());
+
+    let mut entities = SlotMap::<TestKey, ()>::with_key();
+    let (a, b) = (entities.insert(()), entities.insert(()));
+    let mut lines = crate::SecSliceMap2::<TestKey, i32, i32>::new();
+    lines.add_items(a, [1], [3]);
+    lines.add_items(b, [2], [4]);
+    lines.add_items(a, [5], [6]);
+    assert_eq!(lines.items(), (&[2, 5][..], &[4, 6][..]));
+    assert_eq!(lines.get_slice(b), Some((&[2][..], &[4][..])));
+}
+
+#[test]
+#[should_panic]
+fn multiple_columns_mismatched_lengths() {
+    let mut map = crate::SlotSliceMap2::<TestKey, i32, i32>::new();
+    map.add_items([1, 2], [1]);
+}
+
+#[test]
+fn slice_headers() {
+    #[derive(Debug, Default, Clone, PartialEq)]
+    struct Polygon {
+        material: u8,
+        bounds: [i32; 2],
+    }
+
+    let mut polygons = SlotSliceMap::<TestKey, i32, Polygon>::new();
+    let a = polygons.add_items_with_header(
+        Polygon {
+            material: 1,
+            bounds: [1, 3],
+        },
+        [1, 2, 3],
+    );
+    let b = polygons.add_items([4, 5]);
+    let c = polygons.add_items_with_header(
+        Polygon {
+            material: 3,
+            bounds: [6, 6],
+        },
+        [6],
+    );
+
+    assert_eq!(polygons.get_header(a).unwrap().material, 1);
+    assert_eq!(polygons.get_header(b), Some(&Polygon::default()));
+    polygons.get_header_mut(b).unwrap().material = 2;
+
+    polygons.remove_slice(a);
+    assert_eq!(polygons.get_header(a), None);
+    assert_eq!(polygons.get_header(c).unwrap().bounds, [6, 6]);
+
+    let mut slices = polygons.iter_with_headers();
+    assert_eq!(slices.len(), 2);
+    let (key, header, slice) = slices.next().unwrap();
+    assert_eq!((key, header.material, slice), (b, 2, &[4, 5][..]));
+    let (key, header, slice) = slices.next().unwrap();
+    assert_eq!((key, header.material, slice), (c, 3, &[6][..]));
+    assert!(slices.next().is_none());
+
+    let mut other = polygons.clone();
+    assert_eq!(polygons, other);
+    other.get_header_mut(c).unwrap().material = 4;
+    assert_ne!(polygons, other);
+}
+
+#[test]
+fn nested_groups() {
+    use crate::NestedSliceMap;
+
+    new_key_type! {
+        pub struct MeshKey;
+    }
+
+    let mut meshes = NestedSliceMap::<MeshKey, TestKey, i32>::new();
+    let a = meshes.add_group([vec![1, 2, 3], vec![4, 5]]);
+    let b = meshes.add_group([[6, 7], [8, 9], [10, 11]]);
+    let c = meshes.add_group([[12]]);
+    assert_eq!(meshes.groups_len(), 3);
+    assert_eq!(meshes.slices_len(), 6);
+
+    assert_eq!(meshes.get_group(a).unwrap(), &[1, 2, 3, 4, 5]);
+    assert_eq!(meshes.get_group(b).unwrap(), &[6, 7, 8, 9, 10, 11]);
+    let b_keys = meshes.group_keys(b).unwrap().to_vec();
+    assert_eq!(meshes.group_of(b_keys[1]), Some(b));
+    assert_eq!(meshes.get_slice(b_keys[2]).unwrap(), &[10, 11]);
+
+    // Growing a group shifts the following groups
+    let a_last = meshes.push_slice(a, [0, 0]).unwrap();
+    meshes.get_slice_mut(a_last).unwrap()[1] = 100;
+    assert_eq!(meshes.get_group(a).unwrap(), &[1, 2, 3, 4, 5, 0, 100]);
+    assert_eq!(meshes.get_slice(b_keys[0]).unwrap(), &[6, 7]);
+
+    // Removing a slice keeps the rest of its group contiguous
+    meshes.remove_slice(b_keys[1]);
+    assert_eq!(meshes.get_group(b).unwrap(), &[6, 7, 10, 11]);
+    assert_eq!(meshes.get_slice(b_keys[2]).unwrap(), &[10, 11]);
+    let slices: Vec<(TestKey, &[i32])> = meshes.iter_group_slices(b).unwrap().collect();
+    assert_eq!(
+        slices,
+        [(b_keys[0], &[6, 7][..]), (b_keys[2], &[10, 11][..])]
+    );
+
+    // Removing a group removes all of its slices
+    meshes.remove_group(a);
+    assert_eq!(meshes.groups_len(), 2);
+    assert_eq!(meshes.slices_len(), 3);
+    assert!(meshes.get_slice(a_last).is_none());
+    assert_eq!(meshes.items(), &[6, 7, 10, 11, 12]);
+    assert_eq!(meshes.get_group(c).unwrap(), &[12]);
+    assert_eq!(meshes.iter_groups().count(), 2);
+
+    meshes.remove_group(b);
+    meshes.remove_group(c);
+    assert!(meshes.is_empty());
+    assert_eq!(meshes.slices_len(), 0);
+}
+
+#[test]
+fn empty_slices_are_not_aliases() {
+    let mut outlines = SlotSliceMap::<TestKey, i32>::new();
+    let a = outlines.add_items([1, 2]);
+    let empty_a = outlines.add_items([]);
+    let empty_b = outlines.add_items([]);
+    assert_eq!(outlines.ref_count(empty_a), Some(1));
+    assert_eq!(outlines.ref_count(empty_b), Some(1));
+    assert_eq!(outlines.iter_unique_slices().count(), 3);
+
+    let mut other = SlotSliceMap::<TestKey, i32>::new();
+    let moved = outlines.move_slice_to(empty_a, &mut other).unwrap();
+    assert_eq!(other.get_slice(moved), Some(&[][..]));
+    assert_eq!(outlines.ref_count(empty_b), Some(1));
+    assert_eq!(outlines[a], [1, 2]);
+
+    let alias = outlines.add_alias(empty_b).unwrap();
+    assert_eq!(outlines.ref_count(alias), Some(2));
+    assert!(outlines.move_slice_to(empty_b, &mut other).is_none());
+}
+
+#[test]
+fn aliased_slices() {
+    let mut outlines = SlotSliceMap::<TestKey, i32>::new();
+    let a = outlines.add_items([1, 2, 3, 4]);
+    let b = outlines.add_items([5, 6]);
+    let a_alias = outlines.add_alias(a).unwrap();
+    let a_sub = outlines.add_sub_alias(a, 1..3).unwrap();
+    assert!(outlines.add_sub_alias(a, 2..5).is_none());
+
+    // Items are stored once
+    assert_eq!(outlines.items_len(), 6);
+    assert_eq!(outlines.slices_len(), 4);
+    assert_eq!(outlines.get_slice(a_alias).unwrap(), &[1, 2, 3, 4]);
+    assert_eq!(outlines.get_slice(a_sub).unwrap(), &[2, 3]);
+    assert_eq!(outlines.ref_count(a), Some(3));
+    assert_eq!(outlines.ref_count(b), Some(1));
+
+    // Writes are visible through every alias
+    outlines[a_sub][0] = 20;
+    assert_eq!(outlines[a], [1, 20, 3, 4]);
+    assert!(outlines.get_many_mut([a, a_alias]).is_none());
+    assert!(outlines.get_many_mut([a_sub, b]).is_some());
+
+    let unique: Vec<&[i32]> = outlines.iter_unique_slices().collect();
+    assert_eq!(unique, [&[1, 20, 3, 4][..], &[5, 6]]);
+
+    let consumed: Vec<(TestKey, Vec<i32>)> = outlines.clone().into_iter().collect();
+    assert_eq!(consumed.len(), 4);
+    assert!(consumed.contains(&(a_sub, vec![20, 3])));
+    assert!(consumed.contains(&(a_alias, vec![1, 20, 3, 4])));
+    assert_eq!(consumed.last(), Some(&(b, vec![5, 6])));
+
+    // Shared items are only removed along with the last reference
+    outlines.remove_slice(a);
+    assert_eq!(outlines.items_len(), 6);
+    assert_eq!(outlines.get_slice(a_sub).unwrap(), &[20, 3]);
+    outlines.remove_slice(a_alias);
+    assert_eq!(outlines.ref_count(a_sub), Some(1));
+    assert_eq!(outlines.items_len(), 6);
+    outlines.remove_slice(a_sub);
+    assert_eq!(outlines.items(), &[5, 6]);
+    assert_eq!(outlines.get_slice(b).unwrap(), &[5, 6]);
+
+    let mut primary = SlotMap::<TestKey, ()>::with_key();
+    let (p, q, r) = (primary.insert(()), primary.insert(()), primary.insert(()));
+    let removed = primary.insert(());
+    primary.remove(removed);
+    let mut shared = SecSliceMap::<TestKey, i32>::new();
+    shared.add_items(p, [7, 8, 9]);
+    assert!(shared.add_alias(p, q));
+    assert!(shared.add_sub_alias(p, r, 2..3));
+    assert!(!shared.add_alias(removed, r));
+    assert_eq!(shared[r], [9]);
+    shared.remove_slice(p);
+    shared.remove_slice(q);
+    assert_eq!(shared.items(), &[7, 8, 9]);
+}
+
+#[test]
+fn sub_slice_keys() {
+    let mut polygons = SlotSliceMap::<TestKey, i32>::new();
+    let a = polygons.add_items([1, 2, 3]);
+    let b = polygons.add_items([4, 5, 6, 7, 8, 9, 10, 11]);
+
+    let sub = polygons.sub_slice_key(b, 3..7).unwrap();
+    assert_eq!(sub.range(), 3..7);
+    assert!(polygons.sub_slice_key(b, 3..9).is_none());
+    assert_eq!(polygons.get_sub_slice(sub).unwrap(), &[7, 8, 9, 10]);
+
+    // Survives removals that shift the parent slice
+    polygons.remove_slice(a);
+    assert_eq!(polygons.get_sub_slice(sub).unwrap(), &[7, 8, 9, 10]);
+    polygons.get_sub_slice_mut(sub).unwrap()[0] = 70;
+    assert_eq!(polygons[b], [4, 5, 6, 70, 8, 9, 10, 11]);
+
+    polygons.remove_slice(b);
+    assert!(polygons.get_sub_slice(sub).is_none());
+}
+
+#[test]
+fn item_lookup() {
+    use crate::ItemKey;
+
+    let mut polygons = SlotSliceMap::<TestKey, i32>::new();
+    let a = polygons.add_items([1, 2, 3]);
+    let empty = polygons.add_items([]);
+    let b = polygons.add_items([4, 5]);
+    let c = polygons.add_items([6, 7, 8, 9]);
+
+    assert_eq!(polygons.slice_of_item(0), Some((a, 0)));
+    assert_eq!(polygons.slice_of_item(2), Some((a, 2)));
+    assert_eq!(polygons.slice_of_item(3), Some((b, 0)));
+    assert_eq!(polygons.slice_of_item(8), Some((c, 3)));
+    assert_eq!(polygons.slice_of_item(9), None);
+    assert_ne!(polygons.slice_of_item(3).map(|(key, _)| key), Some(empty));
+
+    // The index follows removals
+    polygons.remove_slice(b);
+    assert_eq!(polygons.slice_of_item(3), Some((c, 0)));
+    assert_eq!(polygons.slice_of_item(6), Some((c, 3)));
+    assert_eq!(polygons.slice_of_item(7), None);
+
+    let d = polygons.add_items([10]);
+    assert_eq!(polygons.slice_of_item(7), Some((d, 0)));
+
+    let (slice, offset) = polygons.slice_of_item(5).unwrap();
+    let item = ItemKey {
+        slice,
+        offset: offset as u32,
+    };
+    assert_eq!(polygons.get_item(item), Some(&8));
+    *polygons.get_item_mut(item).unwrap() = 80;
+    assert_eq!(polygons[c], [6, 7, 80, 9]);
+    assert_eq!(
+        polygons.get_item(ItemKey {
+            slice: c,
+            offset: 4
+        }),
+        None
+    );
+
+    // Aliases are indexed too
+    let sub = polygons.add_sub_alias(c, 2..4).unwrap();
+    polygons.remove_slice(c);
+    assert_eq!(polygons.slice_of_item(6), Some((sub, 1)));
+    assert_eq!(polygons.slice_of_item(3), None);
+
+    let mut primary = SlotMap::<TestKey, ()>::with_key();
+    let (p, q) = (primary.insert(()), primary.insert(()));
+    let mut secondary = SecSliceMap::<TestKey, i32>::new();
+    secondary.add_items(p, [1, 2]);
+    secondary.add_items(q, [3]);
+    assert_eq!(secondary.slice_of_item(2), Some((q, 0)));
+    secondary.clear();
+    assert_eq!(secondary.slice_of_item(0), None);
+}
+
+#[test]
+#[allow(clippy::single_range_in_vec_init)]
+fn change_tracking() {
+    let mut polygons = SlotSliceMap::<TestKey, i32>::new();
+    let a = polygons.add_items([1, 2, 3]);
+    assert!(!polygons.is_tracking_changes());
+    assert!(polygons.take_dirty_ranges().is_empty());
+
+    polygons.set_change_tracking(true);
+    let b = polygons.add_items([4, 5]);
+    let c = polygons.add_items([6, 7, 8]);
+    let d = polygons.add_items([9, 10]);
+    // Adjacent ranges are merged
+    assert_eq!(polygons.take_dirty_ranges(), [3..10]);
+    assert!(polygons.take_dirty_ranges().is_empty());
+
+    polygons[a][0] = 10;
+    polygons
+        .get_item_mut(crate::ItemKey {
+            slice: d,
+            offset: 1,
+        })
+        .unwrap();
+    assert_eq!(polygons.take_dirty_ranges(), [0..3, 9..10]);
+
+    let sub = polygons.sub_slice_key(c, 1..2).unwrap();
+    polygons.get_sub_slice_mut(sub).unwrap()[0] = 70;
+    polygons.get_many_mut([a, b]).unwrap();
+    assert_eq!(polygons.take_dirty_ranges(), [0..5, 6..7]);
+
+    // Removing shifts all following items
+    polygons.remove_slice(b);
+    assert_eq!(polygons.take_dirty_ranges(), [3..8]);
+    polygons[d][0] = 0;
+    polygons.remove_slice(d);
+    assert!(polygons.take_dirty_ranges().is_empty());
+
+    polygons.set_change_tracking(false);
+    polygons[a][0] = 1;
+    assert!(polygons.take_dirty_ranges().is_empty());
+}
+
+#[test]
+fn slice_versions() {
+    let mut polygons = SlotSliceMap::<TestKey, i32, u8>::new();
+    let a = polygons.add_items([1, 2, 3, 4]);
+    let b = polygons.add_items([5, 6]);
+    let alias = polygons.add_sub_alias(a, 2..4).unwrap();
+    let va = polygons.slice_version(a).unwrap();
+    let vb = polygons.slice_version(b).unwrap();
+    let valias = polygons.slice_version(alias).unwrap();
+    assert!(va < vb && vb < valias);
+    assert_eq!(polygons.map_version(), valias);
+
+    // Reading doesn't change any version
+    assert_eq!(polygons[a], [1, 2, 3, 4]);
+    assert_eq!(polygons.map_version(), valias);
+
+    // Writing to the first half of a is not visible through the alias
+    polygons
+        .get_sub_slice_mut(polygons.sub_slice_key(a, 0..2).unwrap())
+        .unwrap()[0] = 10;
+    assert!(polygons.slice_version(a).unwrap() > valias);
+    assert_eq!(polygons.slice_version(alias), Some(valias));
+    assert_eq!(polygons.slice_version(b), Some(vb));
+
+    // Writing through the alias changes the source slice too
+    polygons[alias][0] = 30;
+    let version = polygons.map_version();
+    assert_eq!(polygons.slice_version(a), Some(version));
+    assert_eq!(polygons.slice_version(alias), Some(version));
+    assert_eq!(polygons.slice_version(b), Some(vb));
+
+    *polygons.get_header_mut(b).unwrap() = 1;
+    assert!(polygons.slice_version(b).unwrap() > version);
+
+    // Removing changes the map version, and re-added keys never repeat a version
+    let version = polygons.map_version();
+    polygons.remove_slice(b);
+    assert_eq!(polygons.slice_version(b), None);
+    assert!(polygons.map_version() > version);
+    let c = polygons.add_items([7]);
+    assert!(polygons.slice_version(c).unwrap() > version + 1);
+
+    let version = polygons.map_version();
+    polygons.clear();
+    assert!(polygons.map_version() > version);
+}
+
+#[test]
+fn undo_redo_journal() {
+    let mut shapes = crate::JournaledSliceMap::<TestKey, i32, u8>::new();
+    assert!(!shapes.undo());
+
+    let a = shapes.add_items([1, 2, 3]);
+    shapes.begin_transaction();
+    let b = shapes.add_items_with_header(7, [4, 5]);
+    assert_eq!(
+        shapes.set_item(
+            crate::ItemKey {
+                slice: a,
+                offset: 1
+            },
+            20
+        ),
+        Some(2)
+    );
+    assert!(shapes.write_items(b, 0, [40, 50]));
+    assert!(!shapes.write_items(b, 1, [0, 0]));
+    shapes.commit_transaction();
+    assert!(shapes.resize_slice(a, 5, 0));
+    assert_eq!(shapes.remove_slice(b), Some(Vec::from([40, 50])));
+    assert_eq!(shapes.get_slice(a), Some(&[1, 20, 3, 0, 0][..]));
+
+    // Removal and resizing are undone one at a time, restoring the original key and header
+    assert!(shapes.undo());
+    assert_eq!(shapes.get_slice(b), Some(&[40, 50][..]));
+    assert_eq!(shapes.get_header(b), Some(&7));
+    assert!(shapes.undo());
+    assert_eq!(shapes.get_slice(a), Some(&[1, 20, 3][..]));
+
+    // The transaction is undone as a whole
+    assert!(shapes.undo());
+    assert_eq!(shapes.get_slice(a), Some(&[1, 2, 3][..]));
+    assert!(!shapes.contains_key(b));
+    assert!(shapes.undo());
+    assert_eq!(shapes.slices_len(), 0);
+    assert!(!shapes.can_undo());
+
+    assert!(shapes.redo());
+    assert!(shapes.redo());
+    assert_eq!(shapes.get_slice(a), Some(&[1, 20, 3][..]));
+    assert_eq!(shapes.get_slice(b), Some(&[40, 50][..]));
+
+    // A new edit discards the redo history
+    shapes.set_item(
+        crate::ItemKey {
+            slice: b,
+            offset: 0,
+        },
+        0,
+    );
+    assert!(!shapes.can_redo());
+    assert!(!shapes.redo());
+
+    // Keys are only released once the history is cleared
+    shapes.remove_slice(b);
+    shapes.clear_history();
+    assert!(!shapes.can_undo());
+    let c = shapes.add_items([6]);
+    assert_ne!(b, c);
+    assert_eq!(shapes.get_slice(b), None);
+}
+
+#[test]
+fn copy_on_write_snapshots() {
+    use alloc::sync::Arc;
+
+    let mut frames = crate::CowSliceMap::<TestKey, i32>::with_chunk_len(4);
+    let a = frames.add_items([1, 2]);
+    let b = frames.add_items([3]);
+    let c = frames.add_items([4, 5]); // Doesn't fit in the first chunk
+    let d = frames.add_items([6, 7, 8, 9, 10]); // Larger than a chunk
+    assert_eq!(frames.chunks_len(), 3);
+    assert_eq!(frames.items_len(), 10);
+    assert_eq!(frames.get_slice(d), Some(&[6, 7, 8, 9, 10][..]));
+
+    let snapshot = frames.snapshot();
+    assert!(Arc::ptr_eq(&frames.slots[0], &snapshot.slots[0]));
+
+    // Writing copies only the touched chunk
+    frames.get_slice_mut(c).unwrap()[0] = 40;
+    assert!(Arc::ptr_eq(&frames.chunks[0], &snapshot.chunks[0]));
+    assert!(!Arc::ptr_eq(&frames.chunks[1], &snapshot.chunks[1]));
+    assert!(Arc::ptr_eq(&frames.chunks[2], &snapshot.chunks[2]));
+    assert_eq!(frames.get_slice(c), Some(&[40, 5][..]));
+    assert_eq!(snapshot.get_slice(c), Some(&[4, 5][..]));
+
+    // Removing only shifts the slices in the same chunk
+    assert_eq!(frames.remove_slice(a), Some((0, 0..2)));
+    assert_eq!(frames.get_slice(a), None);
+    assert_eq!(frames.get_slice(b), Some(&[3][..]));
+    assert_eq!(frames.get_chunk(0), Some(&[3][..]));
+    assert_eq!(snapshot.get_slice(a), Some(&[1, 2][..]));
+    assert!(Arc::ptr_eq(&frames.chunks[2], &snapshot.chunks[2]));
+
+    let slices: Vec<_> = frames.iter_keys_and_slices().collect();
+    assert_eq!(
+        slices,
+        [(b, &[3][..]), (c, &[40, 5][..]), (d, &[6, 7, 8, 9, 10][..])]
+    );
+    assert_eq!(snapshot.iter_slices().count(), 4);
+
+    // A slice reusing the removed slot gets a new key
+    let e = frames.add_items([11]);
+    assert_eq!(frames.get_slice(a), None);
+    assert_eq!(frames.get_slice(e), Some(&[11][..]));
+    assert_eq!(snapshot.get_slice(a), Some(&[1, 2][..]));
+
+    // Adding and removing copy only the touched chunks of the slice table
+    let mut frames = crate::CowSliceMap::<TestKey, i32>::with_chunk_len(4);
+    let keys: Vec<_> = (0..100).map(|i| frames.add_items([i])).collect();
+    let snapshot = frames.snapshot();
+    let added = frames.add_items([100]);
+    assert!(Arc::ptr_eq(&frames.slots[0], &snapshot.slots[0]));
+    assert!(!Arc::ptr_eq(&frames.slots[1], &snapshot.slots[1]));
+    let snapshot = frames.snapshot();
+    frames.remove_slice(keys[1]);
+    assert!(!Arc::ptr_eq(&frames.slots[0], &snapshot.slots[0]));
+    assert!(Arc::ptr_eq(&frames.slots[1], &snapshot.slots[1]));
+    assert_eq!(frames.get_slice(keys[3]), Some(&[3][..]));
+    assert_eq!(frames.get_slice(added), Some(&[100][..]));
+    assert_eq!(frames.iter_slices().len(), 100);
+
+    // Emptied chunks release their items and are reused, so rolling frames don't grow the map
+    let mut frames = crate::CowSliceMap::<TestKey, i32>::with_chunk_len(4);
+    let mut live = Vec::new();
+    for frame in 0..500 {
+        live.push(frames.add_items([frame, frame + 1, frame + 2]));
+        let _snapshot = frames.snapshot();
+        if live.len() > 4 {
+            frames.remove_slice(live.remove(0));
+        }
+    }
+    assert_eq!(frames.slices_len(), 4);
+    assert!(frames.chunks_len() <= 5);
+    assert_eq!(frames.get_slice(live[3]), Some(&[499, 500, 501][..]));
+}
+
+// Checks that a mirror has the same keys, headers and slices as a SlotSliceMap.
+fn assert_mirrors<S>(
+    mirror: &crate::SliceMap<TestKey, i32, S, u8>,
+    map: &SlotSliceMap<TestKey, i32, u8>,
+) where
+    S: crate::SliceStorage<TestKey, crate::SliceEntry<u8>> + Default,
+{
+    assert_eq!(mirror.slices_len(), map.slices_len());
+    for (key, header, slice) in map.iter_with_headers() {
+        assert_eq!(mirror.get_header(key), Some(header));
+        assert_eq!(mirror.get_slice(key), Some(slice));
+    }
+}
+
+#[test]
+fn diff_runs_and_empty_diff() {
+    let mut old = SlotSliceMap::<TestKey, i32, u8>::new();
+    let a = old.add_items([1, 2, 3, 4, 5, 6]);
+    assert!(crate::diff(&old, &old.clone()).is_empty());
+
+    let mut new = old.clone();
+    new[a][1] = 20;
+    new[a][2] = 30;
+    new[a][5] = 60;
+    let diff = crate::diff(&old, &new);
+    assert!(diff.added.is_empty() && diff.removed.is_empty());
+    assert_eq!(
+        diff.modified,
+        [(
+            a,
+            crate::SliceDiff {
+                header: None,
+                len: 6,
+                runs: Vec::from([(1, Vec::from([20, 30])), (5, Vec::from([60]))]),
+            }
+        )]
+    );
+}
+
+#[test]
+fn diff_round_trip() {
+    let mut old = SlotSliceMap::<TestKey, i32, u8>::new();
+    let a = old.add_items([1, 2, 3]);
+    let b = old.add_items_with_header(5, [4, 5]);
+    let c = old.add_items([6, 7, 8, 9]);
+    let d = old.add_items([10]);
+
+    // A diff from an empty map contains every slice
+    let mut mirror = SecSliceMap::<TestKey, i32, u8>::new();
+    mirror.apply(&crate::diff(&SlotSliceMap::new(), &old));
+    assert_mirrors(&mirror, &old);
+
+    let mut new = old.clone();
+    new.remove_slice(b);
+    new[a][0] = 100;
+    *new.get_header_mut(d).unwrap() = 1;
+    let e = new.add_items_with_header(2, [11, 12]);
+    let diff = crate::diff(&old, &new);
+    assert_eq!(diff.removed, [b]);
+    assert_eq!(diff.added, [(e, 2, Vec::from([11, 12]))]);
+    assert_eq!(diff.modified.len(), 2);
+    assert!(!diff.modified.iter().any(|(key, _)| *key == c));
+    mirror.apply(&diff);
+    assert_mirrors(&mirror, &new);
+
+    // A sparse mirror receives the same changes
+    let mut sparse = crate::SparseSliceMap::<TestKey, i32, u8>::new();
+    sparse.apply(&crate::diff(&SlotSliceMap::new(), &old));
+    sparse.apply(&diff);
+    assert_mirrors(&sparse, &new);
+}
+
+#[test]
+fn diff_round_trip_resized_slices() {
+    let mut keys = SlotMap::<TestKey, ()>::with_key();
+    let (a, b, c) = (keys.insert(()), keys.insert(()), keys.insert(()));
+    let mut old = SecSliceMap::<TestKey, i32, u8>::new();
+    old.add_items(a, [1, 2, 3]);
+    old.add_items(b, [4, 5, 6, 7]);
+    old.add_items(c, [8]);
+
+    // Re-adding a key replaces its slice, which may grow or shrink
+    let mut new = old.clone();
+    new.remove_slice(a);
+    new.add_items_with_header(a, 3, [1, 20, 3, 30, 40]);
+    new.remove_slice(b);
+    new.add_items(b, [4, 50]);
+    let diff = crate::diff(&old, &new);
+    let runs = |key| {
+        let (_, slice_diff) = diff
+            .modified
+            .iter()
+            .find(|(other, _)| *other == key)
+            .unwrap();
+        (slice_diff.len, slice_diff.runs.clone())
+    };
+    assert_eq!(
+        runs(a),
+        (
+            5,
+            Vec::from([(1, Vec::from([20])), (3, Vec::from([30, 40]))])
+        )
+    );
+    assert_eq!(runs(b), (2, Vec::from([(1, Vec::from([50]))])));
+
+    let mut mirror = old.clone();
+    mirror.apply(&diff);
+    assert_eq!(mirror, new);
+
+    // Applying the reverse diff restores the old version
+    mirror.apply(&crate::diff(&new, &old));
+    assert_eq!(mirror, old);
+}
+
+#[test]
+fn diff_apply_to_slot_slice_map() {
+    let mut old = SlotSliceMap::<TestKey, i32, u8>::new();
+    let a = old.add_items([1, 2, 3]);
+    let b = old.add_items([4, 5]);
+    let c = old.add_items([6]);
+
+    let mut new = old.clone();
+    new.join_slices(a, c);
+    new.remove_slice(b);
+    *new.get_header_mut(a).unwrap() = 3;
+    let d = new.add_items_with_header(7, [8, 9]);
+    let diff = crate::diff(&old, &new);
+
+    // Removed and modified slices keep their keys, added slices get new ones
+    let mut patched = old.clone();
+    let remap = patched.apply(&diff);
+    assert_eq!(patched.get_slice(a), Some(&[1, 2, 3, 6][..]));
+    assert_eq!(patched.get_header(a), Some(&3));
+    assert!(!patched.contains_key(b));
+    assert!(!patched.contains_key(c));
+    let new_d = remap.get(d).unwrap();
+    assert_eq!(patched.get_slice(new_d), Some(&[8, 9][..]));
+    assert_eq!(patched.get_header(new_d), Some(&7));
+    assert_eq!(patched.slices_len(), new.slices_len());
+    assert_eq!(patched.items_len(), new.items_len());
+}
+
+#[test]
+fn append_maps() {
+    let mut level = SlotSliceMap::<TestKey, i32>::new();
+    let a = level.add_items([1, 2]);
+    let mut chunk = SlotSliceMap::<TestKey, i32>::new();
+    let b = chunk.add_items([3, 4, 5]);
+    let c = chunk.add_items([6]);
+    let alias = chunk.add_sub_alias(b, 1..3).unwrap();
+
+    let remap = level.append(&mut chunk);
+    assert!(chunk.is_empty() && chunk.slices_len() == 0);
+    assert_eq!(remap.len(), 3);
+    assert_eq!(level.items(), [1, 2, 3, 4, 5, 6]);
+    assert_eq!(level[a], [1, 2]);
+    let (b, c, alias) = (
+        remap.get(b).unwrap(),
+        remap.get(c).unwrap(),
+        remap.get(alias).unwrap(),
+    );
+    assert_eq!(level[b], [3, 4, 5]);
+    assert_eq!(level[c], [6]);
+    assert_eq!(level[alias], [4, 5]);
+    assert_eq!(level.ref_count(b), Some(2));
+    assert_eq!(level.slice_of_item(5), Some((c, 0)));
+
+    // Secondary maps keep their keys, and refuse to merge colliding keys
+    let mut keys = SlotMap::<TestKey, ()>::with_key();
+    let (d, e) = (keys.insert(()), keys.insert(()));
+    let mut left = SecSliceMap::<TestKey, i32>::new();
+    left.add_items(d, [7, 8]);
+    let mut right = SecSliceMap::<TestKey, i32>::new();
+    right.add_items(e, [9]);
+    let mut colliding = right.clone();
+    assert_eq!(left.append(&mut right), Ok(()));
+    assert!(right.is_empty());
+    assert_eq!(left[d], [7, 8]);
+    assert_eq!(left[e], [9]);
+    assert_eq!(
+        left.append(&mut colliding),
+        Err(crate::SliceMapError::KeyCollision(e))
+    );
+    assert_eq!(colliding[e], [9]);
+    assert_eq!(left.items_len(), 3);
+}
+
+#[test]
+#[allow(clippy::single_range_in_vec_init)]
+fn split_off_and_partition() {
+    let mut layer = SlotSliceMap::<TestKey, i32, u8>::new();
+    let a = layer.add_items([1, 2]);
+    let b = layer.add_items_with_header(1, [3, 4, 5]);
+    let c = layer.add_items([6]);
+    let d = layer.add_items_with_header(1, [7, 8]);
+    let alias = layer.add_sub_alias(d, 1..2).unwrap();
+    layer.set_change_tracking(true);
+
+    let selection = layer.split_off([b, d]);
+    assert_eq!(layer.items(), [1, 2, 6, 7, 8]);
+    assert_eq!(selection.items(), [3, 4, 5, 7, 8]);
+    assert_eq!(layer[a], [1, 2]);
+    assert_eq!(layer[c], [6]);
+    assert_eq!(layer[alias], [8]);
+    assert!(!layer.contains_key(b) && !layer.contains_key(d));
+    assert_eq!(selection[b], [3, 4, 5]);
+    assert_eq!(selection[d], [7, 8]);
+    assert_eq!(selection.get_header(d), Some(&1));
+    assert!(!selection.contains_key(a));
+    assert_eq!(layer.slice_of_item(2), Some((c, 0)));
+    assert_eq!(layer.slice_of_item(4), Some((alias, 0)));
+    assert_eq!(selection.slice_of_item(1), Some((b, 1)));
+    assert_eq!(layer.take_dirty_ranges(), [2..5]);
+
+    // Items shared with an alias are no longer shared between the maps
+    layer[alias][0] = 80;
+    assert_eq!(selection[d], [7, 8]);
+
+    let mut selection = selection;
+    let headers = selection.partition_slices(|_, header, slice| *header == 1 && slice.len() > 2);
+    assert_eq!(headers.items(), [3, 4, 5]);
+    assert_eq!(selection.items(), [7, 8]);
+    assert_eq!(selection[d], [7, 8]);
+    assert_eq!(selection.slice_of_item(0), Some((d, 0)));
+}
+
+#[test]
+fn join_slices_around_empty_slice() {
+    let mut lines = SlotSliceMap::<TestKey, i32>::new();
+    let a = lines.add_items([1, 2]);
+    let empty = lines.add_items([]);
+    let b = lines.add_items([3, 4, 5]);
+
+    // The empty slice between a and b keeps its own position
+    assert!(lines.join_slices(a, b));
+    assert_eq!(lines[a], [1, 2, 3, 4, 5]);
+    lines.remove_slice(a);
+    assert_eq!(lines.get_slice(empty), Some(&[][..]));
+    assert!(lines.items().is_empty());
+
+    let mut lines = SlotSliceMap::<TestKey, i32>::new();
+    let a = lines.add_items([1, 2]);
+    let empty = lines.add_items([]);
+    let b = lines.add_items([3, 4, 5]);
+    assert!(lines.join_slices(a, b));
+    let joined = lines.split_off([a]);
+    assert_eq!(joined[a], [1, 2, 3, 4, 5]);
+    assert_eq!(lines.get_slice(empty), Some(&[][..]));
+}
+
+#[test]
+fn split_and_join_slices() {
+    let mut lines = SlotSliceMap::<TestKey, i32, u8>::new();
+    let a = lines.add_items_with_header(3, [1, 2, 3, 4, 5]);
+    let b = lines.add_items([6, 7]);
+    assert_eq!(lines.split_slice(a, 6), None);
+
+    // Splitting never moves items
+    let tail = lines.split_slice(a, 2).unwrap();
+    assert_eq!(lines.items(), [1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(lines[a], [1, 2]);
+    assert_eq!(lines[tail], [3, 4, 5]);
+    assert_eq!(lines.get_header(tail), Some(&3));
+    assert_eq!(lines.ref_count(tail), Some(1));
+    assert_eq!(lines.slice_of_item(2), Some((tail, 0)));
+
+    // Both halves stay independent
+    lines[a][0] = 10;
+    assert_eq!(lines[tail], [3, 4, 5]);
+
+    // Adjacent slices are joined in place
+    assert!(lines.join_slices(a, tail));
+    assert!(!lines.contains_key(tail));
+    assert_eq!(lines.items(), [10, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(lines[a], [10, 2, 3, 4, 5]);
+    assert_eq!(lines.slice_of_item(4), Some((a, 4)));
+
+    // Other slices are moved to the end
+    assert!(lines.join_slices(b, a));
+    assert_eq!(lines.items(), [6, 7, 10, 2, 3, 4, 5]);
+    assert_eq!(lines[b], [6, 7, 10, 2, 3, 4, 5]);
+    assert_eq!(lines.slices_len(), 1);
+    assert!(!lines.join_slices(b, b));
+    assert!(!lines.join_slices(b, a));
+
+    // Splits of a slice shared with an alias still share its items
+    let alias = lines.add_alias(b).unwrap();
+    let tail = lines.split_slice(b, 3).unwrap();
+    lines[tail][0] = 20;
+    assert_eq!(lines[alias], [6, 7, 10, 20, 3, 4, 5]);
+    assert!(lines.join_slices(b, tail));
+    assert_eq!(lines[b], [6, 7, 10, 20, 3, 4, 5]);
+    assert_eq!(lines.items_len(), 14);
+    lines.remove_slice(alias);
+    assert_eq!(lines.items(), [6, 7, 10, 20, 3, 4, 5]);
+
+    // Secondary maps receive the key for the second half
+    let mut keys = SlotMap::<TestKey, ()>::with_key();
+    let (c, d) = (keys.insert(()), keys.insert(()));
+    let mut sec = SecSliceMap::<TestKey, i32>::new();
+    sec.add_items(c, [1, 2, 3]);
+    assert!(!sec.split_slice(c, 1, c));
+    assert!(sec.split_slice(c, 1, d));
+    assert_eq!(sec[c], [1]);
+    assert_eq!(sec[d], [2, 3]);
+}
+
+#[test]
+fn duplicate_and_move_slices() {
+    let mut layer = SlotSliceMap::<TestKey, i32, u8>::new();
+    let a = layer.add_items_with_header(4, [1, 2]);
+    let b = layer.add_items([3]);
+    let copy = layer.duplicate_slice(a).unwrap();
+    assert_eq!(layer.items(), [1, 2, 3, 1, 2]);
+    assert_eq!(layer.get_header(copy), Some(&4));
+    layer[copy][0] = 10;
+    assert_eq!(layer[a], [1, 2]);
+
+    let mut other = SlotSliceMap::<TestKey, i32, u8>::new();
+    other.add_items([0]);
+    let moved = layer.move_slice_to(a, &mut other).unwrap();
+    assert!(!layer.contains_key(a));
+    assert_eq!(layer.items(), [3, 10, 2]);
+    assert_eq!(layer[b], [3]);
+    assert_eq!(layer.slice_of_item(1), Some((copy, 0)));
+    assert_eq!(other[moved], [1, 2]);
+    assert_eq!(other.get_header(moved), Some(&4));
+
+    // Aliased slices can't be moved without cloning
+    let alias = layer.add_alias(b).unwrap();
+    assert_eq!(layer.move_slice_to(alias, &mut other), None);
+    assert_eq!(layer.duplicate_slice(a), None);
+}
+
+#[test]
+fn map_items_and_slices() {
+    let mut world = SlotSliceMap::<TestKey, i32, u8>::new();
+    let a = world.add_items_with_header(1, [1, 2, 3]);
+    let b = world.add_items([4, 5]);
+    let c = world.add_items([6]);
+    let alias = world.add_sub_alias(a, 1..3).unwrap();
+    world.remove_slice(b);
+
+    let mut screen = world.map_items(|item| *item as f32 * 0.5);
+    assert_eq!(screen[a], [0.5, 1.0, 1.5]);
+    assert_eq!(screen[c], [3.0]);
+    assert_eq!(screen[alias], [1.0, 1.5]);
+    assert_eq!(screen.get_header(a), Some(&1));
+    assert_eq!(screen.slice_of_item(3), Some((c, 0)));
+
+    // Each run of shared items is converted once
+    let mut calls = 0;
+    let mut offsets = world.map_slices(|slice| {
+        calls += 1;
+        let first = slice[0];
+        slice
+            .iter()
+            .map(move |item| item - first)
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(calls, 2);
+    assert_eq!(offsets[a], [0, 1, 2]);
+    assert_eq!(offsets[alias], [1, 2]);
+    assert_eq!(offsets[c], [0]);
+    assert_eq!(offsets.slice_of_item(3), Some((c, 0)));
+
+    // Versions keep increasing in the new maps
+    let before = screen.slice_version(c).unwrap();
+    screen[c][0] = 0.0;
+    assert!(screen.slice_version(c).unwrap() > before);
+    assert!(screen.map_version() > world.map_version());
+    let before = offsets.slice_version(a).unwrap().max(world.map_version());
+    offsets[alias][0] = 5;
+    assert!(offsets.slice_version(a).unwrap() > before);
+}
+
+#[test]
+#[should_panic(expected = "as many items")]
+fn map_slices_wrong_length() {
+    let mut world = SlotSliceMap::<TestKey, i32>::new();
+    world.add_items([1, 2, 3]);
+    world.map_slices(|slice| slice[..1].to_vec());
+}
+
+#[test]
+fn zip_slices_with_same_layout() {
+    let mut positions = SlotSliceMap::<TestKey, i32>::new();
+    let a = positions.add_items([1, 2]);
+    let b = positions.add_items([3]);
+    let normals = positions.map_items(|item| -item);
+
+    let zipped: Vec<_> = crate::zip_slices(&positions, &normals).unwrap().collect();
+    assert_eq!(
+        zipped,
+        [(a, &[1, 2][..], &[-1, -2][..]), (b, &[3][..], &[-3][..])]
+    );
+    assert_eq!(
+        crate::zip_slices(&positions, &normals).unwrap().next_back(),
+        Some((b, &[3][..], &[-3][..]))
+    );
+
+    // Different ranges or different keys are rejected
+    let mut moved = normals.clone();
+    moved.remove_slice(a);
+    assert_eq!(
+        crate::zip_slices(&positions, &moved).err(),
+        Some(crate::SliceMapError::LayoutMismatch(a))
+    );
+    let mut extra = positions.clone();
+    let c = extra.add_items([4]);
+    assert_eq!(
+        crate::zip_slices(&extra, &normals).err(),
+        Some(crate::SliceMapError::LayoutMismatch(c))
+    );
+    let mut other = SlotSliceMap::<TestKey, i32>::new();
+    other.add_items([5, 6]);
+    let d = other.add_items([7]);
+    other.remove_slice(d);
+    other.add_items([8]);
+    assert!(crate::zip_slices(&positions, &other).is_err());
+}
+
+#[test]
+fn hash_and_btree_storage() {
+    use alloc::string::{String, ToString};
+
+    let mut meshes = crate::HashSliceMap::<String, i32>::new();
+    meshes.add_items("tree".to_string(), [1, 2, 3]);
+    meshes.add_items("rock".to_string(), [4, 5]);
+    assert_eq!(meshes["tree".to_string()], [1, 2, 3]);
+    assert_eq!(meshes.slice_of_item(3), Some(("rock".to_string(), 0)));
+
+    // Adding items with a present key replaces its slice, removing the unshared items
+    meshes.add_items("tree".to_string(), [1, 2, 3]);
+    assert_eq!(meshes.items(), [4, 5, 1, 2, 3]);
+    assert!(meshes.add_alias("tree".to_string(), "bush".to_string()));
+    meshes.add_items("tree".to_string(), [1, 2, 3]);
+    assert_eq!(meshes.items(), [4, 5, 1, 2, 3, 1, 2, 3]);
+    meshes.remove_slice("bush".to_string());
+    assert_eq!(meshes.items(), [4, 5, 1, 2, 3]);
+    assert_eq!(meshes.slice_of_item(2), Some(("tree".to_string(), 0)));
+    meshes.remove_slice("tree".to_string());
+    assert_eq!(meshes.items(), [4, 5]);
+    assert_eq!(meshes.get_slice("rock".to_string()), Some(&[4, 5][..]));
+
+    let mut ids = crate::BTreeSliceMap::<u64, i32>::new();
+    ids.add_items(30, [3]);
+    ids.add_items(10, [1, 1]);
+    ids.add_items(20, [2]);
+    assert!(ids.split_slice(10, 1, 15));
+    let keys: Vec<u64> = ids.range(..).map(|(key, _)| key).collect();
+    assert_eq!(keys, [10, 15, 20, 30]);
+    let in_range: Vec<_> = ids.range(12..=20).collect();
+    assert_eq!(in_range, [(15, &[1][..]), (20, &[2][..])]);
+    assert_eq!(ids.range(..).next_back(), Some((30, &[3][..])));
+
+    // Splitting works with any key type
+    let small = ids.partition_slices(|_, _, slice| slice == [1]);
+    assert_eq!(small.items(), [1, 1]);
+    assert_eq!(small.get_slice(15), Some(&[1][..]));
+    let high = ids.split_off([30]);
+    assert_eq!(high.items(), [3]);
+    assert_eq!(ids.items(), [2]);
+}
+
+#[test]
+fn dense_and_hop_storage() {
+    let mut dense = crate::DenseSliceMap::<TestKey, i32>::new();
+    let a = dense.add_items([1, 2, 3]);
+    let b = dense.add_items([4, 5]);
+    let c = dense.add_alias(b).unwrap();
+    dense.remove_slice(a);
+    assert_eq!(dense.items(), [4, 5]);
+    assert_eq!(dense.get_slice(c), Some(&[4, 5][..]));
+    let d = dense.split_slice(b, 1).unwrap();
+    assert_eq!(dense.get_slice(d), Some(&[5][..]));
+    let collected: crate::DenseSliceMap<TestKey, i32> = [&[1, 2][..], &[3]].into_iter().collect();
+    assert_eq!(collected.items(), [1, 2, 3]);
+
+    let mut hop = crate::HopSliceMap::<TestKey, i32>::new();
+    let keys: Vec<_> = (0..10).map(|i| hop.add_items([i, i])).collect();
+    for key in &keys[1..9] {
+        hop.remove_slice(*key);
+    }
+    let slices: Vec<_> = hop.iter_keys_and_slices().collect();
+    assert_eq!(slices, [(keys[0], &[0, 0][..]), (keys[9], &[9, 9][..])]);
+    let copy = hop.duplicate_slice(keys[9]).unwrap();
+    assert_eq!(hop[copy], [9, 9]);
+    hop.extend([[10, 10]]);
+    assert_eq!(hop.items(), [0, 0, 9, 9, 9, 9, 10, 10]);
+}
+
+#[test]
+#[allow(clippy::single_range_in_vec_init)]
+fn retain_alive_and_sync() {
+    let mut entities = SlotMap::<TestKey, &str>::with_key();
+    let a = entities.insert("a");
+    let b = entities.insert("b");
+    let c = entities.insert("c");
+    let d = entities.insert("d");
+
+    let mut meshes = SecSliceMap::<TestKey, i32>::new();
+    meshes.set_change_tracking(true);
+    meshes.add_items(a, [1, 1]);
+    meshes.add_items(b, [2, 2, 2]);
+    meshes.add_items(c, [3]);
+    assert!(meshes.add_alias(c, d));
+    meshes.take_dirty_ranges();
+
+    entities.remove(a);
+    entities.remove(c);
+    assert_eq!(meshes.retain_alive(&entities), 2);
+    assert_eq!(meshes.items(), [2, 2, 2, 3]); // Still used by the alias
+    assert_eq!(meshes.get_slice(b), Some(&[2, 2, 2][..]));
+    assert_eq!(meshes.get_slice(d), Some(&[3][..]));
+    assert_eq!(meshes.slice_of_item(3), Some((d, 0)));
+    assert_eq!(meshes.take_dirty_ranges(), [0..4]);
+    assert_eq!(meshes.retain_alive(&entities), 0);
+
+    let mut primary = SlotSliceMap::<TestKey, i32>::new();
+    let e = primary.add_items([5]);
+    let mut colors = SecSliceMap::<TestKey, u8>::new();
+    colors.add_items(e, [1, 2]);
+    colors.add_items(b, [3]);
+    assert_eq!(colors.sync_with(&primary), 1);
+    assert_eq!(colors.items(), [1, 2]);
+
+    let mut lengths = SlotSliceMap::<TestKey, i32>::new();
+    let short = lengths.add_items([1]);
+    lengths.add_items([1, 2, 3]);
+    let kept = lengths.add_items([4]);
+    assert_eq!(lengths.retain_slices(|_, _, slice| slice.len() < 2), 1);
+    assert_eq!(lengths.items(), [1, 4]);
+    assert_eq!(lengths[kept], [4]);
+    assert_eq!(lengths.slice_of_item(0), Some((short, 0)));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "stale key")]
+fn stale_key_checks() {
+    let mut entities = SlotMap::<TestKey, ()>::with_key();
+    let a = entities.insert(());
+    let b = entities.insert(());
+    let mut meshes = SecSliceMap::<TestKey, i32>::new();
+    meshes.set_stale_key_checks(true);
+    meshes.add_items(a, [1]);
+    meshes.add_items(b, [2]);
+    entities.remove(a);
+    entities.remove(b);
+    meshes.retain_alive(&entities);
+    assert_eq!(meshes.get_slice(entities.insert(())), None); // Never added, not stale
+
+    // Appending a slice with a stale key makes it valid again
+    let mut restored = SecSliceMap::<TestKey, i32>::new();
+    restored.add_items(b, [3]);
+    meshes.append(&mut restored).unwrap();
+    meshes.remove_slice(b);
+    assert_eq!(meshes.get_slice(b), None);
+    meshes.get_slice(a);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn stale_key_checks_every_lookup() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    let mut entities = SlotMap::<TestKey, ()>::with_key();
+    let a = entities.insert(());
+    let mut meshes = SecSliceMap::<TestKey, i32>::new();
+    meshes.set_stale_key_checks(true);
+    meshes.add_items(a, [1, 2]);
+    let sub = meshes.sub_slice_key(a, 0..1).unwrap();
+    let item = crate::ItemKey {
+        slice: a,
+        offset: 1,
+    };
+    entities.remove(a);
+    meshes.retain_alive(&entities);
+    assert!(!meshes.contains_key(a)); // Only lookups that expect the key are checked
+
+    type Lookup<'a> = dyn Fn(&mut SecSliceMap<TestKey, i32>) + 'a;
+    let lookups: [&Lookup; 7] = [
+        &|map| _ = map.get_item_mut(item),
+        &|map| _ = map.get_sub_slice_mut(sub),
+        &|map| _ = map.sub_slice_key(a, 0..1),
+        &|map| _ = map.remove_slice(a),
+        &|map| _ = map.get_many_mut([a]),
+        &|map| _ = map.get_header_mut(a),
+        &|map| _ = map.ref_count(a),
+    ];
+    for lookup in lookups {
+        let result = catch_unwind(AssertUnwindSafe(|| lookup(&mut meshes)));
+        assert!(result.is_err());
+    }
+
+    // Applying a diff skips stale keys instead of looking them up
+    let mut copy = meshes.clone();
+    copy.add_items(a, [3]);
+    meshes.apply(&crate::diff(&meshes, &copy));
+    assert_eq!(meshes[a], [3]);
+}
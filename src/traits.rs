@@ -1,14 +1,34 @@
-use alloc::boxed::Box;
-use slotmap::{Key, SecondaryMap, SlotMap, SparseSecondaryMap};
+use alloc::collections::{btree_map, BTreeMap};
+use core::hash::Hash;
+use core::iter::{FusedIterator, Map};
+#[allow(deprecated)]
+use slotmap::HopSlotMap;
+use slotmap::{DenseSlotMap, Key, SecondaryMap, SlotMap, SparseSecondaryMap};
+use std::collections::{hash_map, HashMap};
 
 /// Trait to abstract operations on storage of slices
+/// The iterators are the storage's own, so they keep its size and reverse iteration support.
 pub trait SliceStorage<K, V>: Default {
+    type Iter<'a>: ExactSizeIterator<Item = (K, &'a V)> + FusedIterator
+    where
+        Self: 'a,
+        V: 'a;
+    type Values<'a>: ExactSizeIterator<Item = &'a V> + FusedIterator
+    where
+        Self: 'a,
+        V: 'a;
+    type ValuesMut<'a>: ExactSizeIterator<Item = &'a mut V> + FusedIterator
+    where
+        Self: 'a,
+        V: 'a;
+
     fn insert(&mut self, value: V) -> K;
-    fn remove(&mut self, key: K) -> Option<V>;
-    fn get(&self, key: K) -> Option<&V>;
-    fn iter(&self) -> Box<dyn Iterator<Item = (K, &V)> + '_>;
-    fn values(&self) -> Box<dyn Iterator<Item = &V> + '_>;
-    fn values_mut(&mut self) -> Box<dyn Iterator<Item = &mut V> + '_>;
+    fn remove(&mut self, key: &K) -> Option<V>;
+    fn get(&self, key: &K) -> Option<&V>;
+    fn get_mut(&mut self, key: &K) -> Option<&mut V>;
+    fn iter(&self) -> Self::Iter<'_>;
+    fn values(&self) -> Self::Values<'_>;
+    fn values_mut(&mut self) -> Self::ValuesMut<'_>;
     fn is_empty(&self) -> bool;
 }
 
@@ -17,34 +37,180 @@ where
     K: Key,
 {
 
+    type Iter<'a>
+        = slotmap::basic::Iter<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type Values<'a>
+        = slotmap::basic::Values<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type ValuesMut<'a>
+        = slotmap::basic::ValuesMut<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+
     #[inline(always)]
     fn insert(&mut self, value: V) -> K {
         self.insert(value)
     }
 
     #[inline(always)]
-    fn remove(&mut self, key: K) -> Option<V> {
-        self.remove(key)
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(*key)
     }
 
     #[inline(always)]
-    fn get(&self, key: K) -> Option<&V> {
-        self.get(key)
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(*key)
+    }
+
+    #[inline(always)]
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut(*key)
+    }
+
+    #[inline(always)]
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    #[inline(always)]
+    fn values(&self) -> Self::Values<'_> {
+        self.values()
+    }
+
+    #[inline(always)]
+    fn values_mut(&mut self) -> Self::ValuesMut<'_> {
+        self.values_mut()
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl<K, V> SliceStorage<K, V> for DenseSlotMap<K, V>
+where
+    K: Key,
+{
+
+    type Iter<'a>
+        = slotmap::dense::Iter<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type Values<'a>
+        = slotmap::dense::Values<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type ValuesMut<'a>
+        = slotmap::dense::ValuesMut<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    #[inline(always)]
+    fn insert(&mut self, value: V) -> K {
+        self.insert(value)
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(*key)
+    }
+
+    #[inline(always)]
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(*key)
     }
 
     #[inline(always)]
-    fn iter(&self) -> Box<dyn Iterator<Item = (K, &V)> + '_> {
-        Box::new(self.iter())
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut(*key)
     }
 
     #[inline(always)]
-    fn values(&self) -> Box<dyn Iterator<Item = &V> + '_> {
-        Box::new(self.values())
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
     }
 
     #[inline(always)]
-    fn values_mut(&mut self) -> Box<dyn Iterator<Item = &mut V> + '_> {
-        Box::new(self.values_mut())
+    fn values(&self) -> Self::Values<'_> {
+        self.values()
+    }
+
+    #[inline(always)]
+    fn values_mut(&mut self) -> Self::ValuesMut<'_> {
+        self.values_mut()
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+#[allow(deprecated)]
+impl<K, V> SliceStorage<K, V> for HopSlotMap<K, V>
+where
+    K: Key,
+{
+
+    type Iter<'a>
+        = slotmap::hop::Iter<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type Values<'a>
+        = slotmap::hop::Values<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type ValuesMut<'a>
+        = slotmap::hop::ValuesMut<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    #[inline(always)]
+    fn insert(&mut self, value: V) -> K {
+        self.insert(value)
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(*key)
+    }
+
+    #[inline(always)]
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(*key)
+    }
+
+    #[inline(always)]
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut(*key)
+    }
+
+    #[inline(always)]
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    #[inline(always)]
+    fn values(&self) -> Self::Values<'_> {
+        self.values()
+    }
+
+    #[inline(always)]
+    fn values_mut(&mut self) -> Self::ValuesMut<'_> {
+        self.values_mut()
     }
 
     #[inline(always)]
@@ -57,34 +223,55 @@ impl<K, V> SliceStorage<K, V> for SecondaryMap<K, V>
 where
     K: Key,
 {
+    type Iter<'a>
+        = slotmap::secondary::Iter<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type Values<'a>
+        = slotmap::secondary::Values<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type ValuesMut<'a>
+        = slotmap::secondary::ValuesMut<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+
     #[inline(always)]
     fn insert(&mut self, _value: V) -> K {
         panic!("SecondaryMap does not support insert; keys must be pre-created")
     }
 
     #[inline(always)]
-    fn remove(&mut self, key: K) -> Option<V> {
-        self.remove(key)
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(*key)
     }
 
     #[inline(always)]
-    fn get(&self, key: K) -> Option<&V> {
-        self.get(key)
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(*key)
+    }
+
+    #[inline(always)]
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut(*key)
     }
 
     #[inline(always)]
-    fn iter(&self) -> Box<dyn Iterator<Item = (K, &V)> + '_> {
-        Box::new(self.iter())
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
     }
 
     #[inline(always)]
-    fn values(&self) -> Box<dyn Iterator<Item = &V> + '_> {
-        Box::new(self.values())
+    fn values(&self) -> Self::Values<'_> {
+        self.values()
     }
 
     #[inline(always)]
-    fn values_mut(&mut self) -> Box<dyn Iterator<Item = &mut V> + '_> {
-        Box::new(self.values_mut())
+    fn values_mut(&mut self) -> Self::ValuesMut<'_> {
+        self.values_mut()
     }
 
     #[inline(always)]
@@ -98,34 +285,116 @@ impl<K, V> SliceStorage<K, V> for SparseSecondaryMap<K, V>
 where
     K: Key,
 {
+    type Iter<'a>
+        = slotmap::sparse_secondary::Iter<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type Values<'a>
+        = slotmap::sparse_secondary::Values<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type ValuesMut<'a>
+        = slotmap::sparse_secondary::ValuesMut<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+
     #[inline(always)]
     fn insert(&mut self, _value: V) -> K {
         panic!("SecondaryMap does not support insert; keys must be pre-created")
     }
 
     #[inline(always)]
-    fn remove(&mut self, key: K) -> Option<V> {
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(*key)
+    }
+
+    #[inline(always)]
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(*key)
+    }
+
+    #[inline(always)]
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut(*key)
+    }
+
+    #[inline(always)]
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    #[inline(always)]
+    fn values(&self) -> Self::Values<'_> {
+        self.values()
+    }
+
+    #[inline(always)]
+    fn values_mut(&mut self) -> Self::ValuesMut<'_> {
+        self.values_mut()
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl<K, V> SliceStorage<K, V> for HashMap<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Iter<'a>
+        = Map<hash_map::Iter<'a, K, V>, fn((&'a K, &'a V)) -> (K, &'a V)>
+    where
+        Self: 'a,
+        V: 'a;
+    type Values<'a>
+        = hash_map::Values<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type ValuesMut<'a>
+        = hash_map::ValuesMut<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    #[inline(always)]
+    fn insert(&mut self, _value: V) -> K {
+        panic!("HashMap does not support insert; keys must be provided")
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, key: &K) -> Option<V> {
         self.remove(key)
     }
 
     #[inline(always)]
-    fn get(&self, key: K) -> Option<&V> {
+    fn get(&self, key: &K) -> Option<&V> {
         self.get(key)
     }
 
     #[inline(always)]
-    fn iter(&self) -> Box<dyn Iterator<Item = (K, &V)> + '_> {
-        Box::new(self.iter())
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut(key)
     }
 
     #[inline(always)]
-    fn values(&self) -> Box<dyn Iterator<Item = &V> + '_> {
-        Box::new(self.values())
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter().map(clone_key)
     }
 
     #[inline(always)]
-    fn values_mut(&mut self) -> Box<dyn Iterator<Item = &mut V> + '_> {
-        Box::new(self.values_mut())
+    fn values(&self) -> Self::Values<'_> {
+        self.values()
+    }
+
+    #[inline(always)]
+    fn values_mut(&mut self) -> Self::ValuesMut<'_> {
+        self.values_mut()
     }
 
     #[inline(always)]
@@ -133,3 +402,69 @@ where
         self.is_empty()
     }
 }
+
+impl<K, V> SliceStorage<K, V> for BTreeMap<K, V>
+where
+    K: Clone + Ord,
+{
+    type Iter<'a>
+        = Map<btree_map::Iter<'a, K, V>, fn((&'a K, &'a V)) -> (K, &'a V)>
+    where
+        Self: 'a,
+        V: 'a;
+    type Values<'a>
+        = btree_map::Values<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+    type ValuesMut<'a>
+        = btree_map::ValuesMut<'a, K, V>
+    where
+        Self: 'a,
+        V: 'a;
+
+    #[inline(always)]
+    fn insert(&mut self, _value: V) -> K {
+        panic!("BTreeMap does not support insert; keys must be provided")
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+
+    #[inline(always)]
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    #[inline(always)]
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut(key)
+    }
+
+    #[inline(always)]
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter().map(clone_key)
+    }
+
+    #[inline(always)]
+    fn values(&self) -> Self::Values<'_> {
+        self.values()
+    }
+
+    #[inline(always)]
+    fn values_mut(&mut self) -> Self::ValuesMut<'_> {
+        self.values_mut()
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+// Turns a borrowed key from a standard map into an owned one, for SliceStorage::iter.
+fn clone_key<'a, K: Clone, V>((key, value): (&'a K, &'a V)) -> (K, &'a V) {
+    (key.clone(), value)
+}
@@ -0,0 +1,75 @@
+use alloc::vec::Vec;
+
+use crate::{SliceEntry, SliceMap, SliceStorage};
+
+impl<K, V, S, H> SliceMap<K, V, S, H>
+where
+    K: Clone + Eq,
+    S: SliceStorage<K, SliceEntry<H>> + Default + Clone,
+{
+    /// Returns a new SliceMap with every item converted by the function, keeping the same
+    /// keys, headers and layout, so keys from this map are directly valid in the new one.
+    pub fn map_items<U, F>(&self, f: F) -> SliceMap<K, U, S, H>
+    where
+        F: FnMut(&V) -> U,
+    {
+        let mut result = SliceMap::new();
+        result.version = self.version; // Keeps the cloned slice versions from repeating
+        result.items = self.items.iter().map(f).collect();
+        result.slices = self.slices.clone();
+        result.index = self.index.clone();
+        result
+    }
+
+    /// Returns a new SliceMap where the items of each slice are converted at once by the function,
+    /// which must return as many items as it receives. Keys and headers stay the same, so keys
+    /// from this map are directly valid in the new one. Items shared by aliases are converted
+    /// once, and the function receives all the shared items.
+    /// Will panic if the function returns a different number of items.
+    pub fn map_slices<U, F, ITEMS>(&self, mut f: F) -> SliceMap<K, U, S, H>
+    where
+        F: FnMut(&[V]) -> ITEMS,
+        ITEMS: IntoIterator<Item = U>,
+    {
+        let mut result = SliceMap::new();
+        result.version = self.version; // Keeps the cloned slice versions from repeating
+        result.slices = self.slices.clone();
+
+        // Convert each block of items once, in item order
+        let mut blocks: Vec<_> = self
+            .slices
+            .values()
+            .map(|entry| (entry.block.clone(), entry.block_id))
+            .collect();
+        blocks.sort_unstable_by_key(|(block, id)| (block.start, block.end, *id));
+        blocks.dedup_by_key(|(_, id)| *id);
+        let mut starts = Vec::with_capacity(blocks.len());
+        for (block, _) in &blocks {
+            let start = result.items.len();
+            result
+                .items
+                .extend(f(&self.items[block.start as usize..block.end as usize]));
+            assert_eq!(
+                result.items.len() - start,
+                block.len(),
+                "map_slices must return as many items as it receives"
+            );
+            starts.push(start as u32);
+        }
+
+        // Items not used by any slice are dropped, so the ranges may move
+        for entry in result.slices.values_mut() {
+            let i = blocks.partition_point(|(block, id)| {
+                (block.start, block.end, *id) < (entry.block.start, entry.block.end, entry.block_id)
+            });
+            let offset = entry.block.start - starts[i];
+            entry.block = entry.block.start - offset..entry.block.end - offset;
+            entry.range = entry.range.start - offset..entry.range.end - offset;
+        }
+        for (_, key) in &self.index {
+            let range = result.slices.get(key).unwrap().range.clone();
+            result.index.push((range, key.clone()));
+        }
+        result
+    }
+}